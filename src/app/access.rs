@@ -0,0 +1,217 @@
+// MQTT peers have no address to check, so an MQTT-only build never checks any
+#![cfg_attr(
+    not(any(
+        feature = "ws",
+        feature = "bluetooth",
+        feature = "tcp",
+        feature = "udp"
+    )),
+    allow(dead_code)
+)]
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    net::IpAddr,
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::anyhow;
+use ipnet::IpNet;
+
+use super::Args;
+
+/// Where a new connection comes from, as far as access lists can tell
+#[derive(Clone, Copy, Debug)]
+pub enum PeerAddr<'a> {
+    #[cfg_attr(
+        not(any(feature = "ws", feature = "tcp", feature = "udp")),
+        allow(dead_code)
+    )]
+    Ip(IpAddr),
+    #[cfg_attr(not(feature = "bluetooth"), allow(dead_code))]
+    Bluetooth(&'a str),
+}
+
+impl fmt::Display for PeerAddr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(ip) => write!(f, "{ip}"),
+            Self::Bluetooth(address) => write!(f, "{address}"),
+        }
+    }
+}
+
+/// Entries blocked from the window while running, on top of `--block`
+static BLOCKED: LazyLock<Mutex<BTreeSet<String>>> = LazyLock::new(|| Mutex::new(BTreeSet::new()));
+
+/// One access list entry: an IP address or CIDR range, or a Bluetooth
+/// address where `*` matches any run of characters
+enum Rule {
+    Net(IpNet),
+    Bluetooth(String),
+}
+
+impl Rule {
+    fn parse(entry: &str) -> anyhow::Result<Self> {
+        let entry = entry.trim();
+        if let Ok(net) = entry.parse::<IpNet>() {
+            return Ok(Self::Net(net));
+        }
+        if let Ok(ip) = entry.parse::<IpAddr>() {
+            return Ok(Self::Net(ip.into()));
+        }
+
+        let pattern = entry.to_ascii_uppercase().replace('-', ":");
+        let is_bluetooth = pattern.contains([':', '*'])
+            && pattern
+                .chars()
+                .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '*');
+        if is_bluetooth {
+            return Ok(Self::Bluetooth(pattern));
+        }
+
+        Err(anyhow!(
+            "`{entry}` is neither an IP address, a CIDR range nor a Bluetooth address"
+        ))
+    }
+
+    /// None when the rule is about another kind of address
+    fn matches(&self, peer: PeerAddr) -> Option<bool> {
+        match (self, peer) {
+            (Self::Net(net), PeerAddr::Ip(ip)) => Some(net.contains(&ip.to_canonical())),
+            (Self::Bluetooth(pattern), PeerAddr::Bluetooth(address)) => {
+                Some(wildcard_match(pattern, &address.to_ascii_uppercase()))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..]))
+        }
+    }
+}
+
+/// Fails on the first entry of `--allow` or `--block` that can't be parsed,
+/// so typos show up at startup
+pub fn validate_access_lists(args: &Args) -> anyhow::Result<()> {
+    for entry in args.allowlist.iter().chain(&args.blocklist) {
+        Rule::parse(entry)?;
+    }
+    Ok(())
+}
+
+/// Rejects peers on the blocklist, and peers missing from the allowlist when
+/// it has entries for their kind of address
+pub fn check_access(args: &Args, peer: PeerAddr) -> anyhow::Result<()> {
+    let blocked = BLOCKED
+        .lock()
+        .map(|blocked| blocked.clone())
+        .unwrap_or_default();
+    for entry in args.blocklist.iter().chain(&blocked) {
+        if let Ok(rule) = Rule::parse(entry)
+            && rule.matches(peer) == Some(true)
+        {
+            return Err(anyhow!("Rejected {peer}: blocked by `{entry}`"));
+        }
+    }
+
+    let allowed = args
+        .allowlist
+        .iter()
+        .filter_map(|entry| Rule::parse(entry).ok()?.matches(peer))
+        .collect::<Vec<_>>();
+    if !allowed.is_empty() && !allowed.contains(&true) {
+        return Err(anyhow!("Rejected {peer}: not on the allowlist"));
+    }
+
+    Ok(())
+}
+
+/// Blocks an address for every transport right away, without a restart
+#[cfg(feature = "ui")]
+pub fn block_address(address: &str) {
+    if let Ok(mut blocked) = BLOCKED.lock() {
+        blocked.insert(address.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(entry: &str, peer: PeerAddr) -> Option<bool> {
+        Rule::parse(entry).unwrap().matches(peer)
+    }
+
+    fn ip(ip: &str) -> PeerAddr<'static> {
+        PeerAddr::Ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn wildcard_corners() {
+        assert!(wildcard_match("", ""));
+        assert!(!wildcard_match("", "A"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*", "AA:BB"));
+        assert!(wildcard_match("**", "AA"));
+        assert!(wildcard_match("AA:*", "AA:"));
+        assert!(wildcard_match("*:FF", "AA:FF"));
+        assert!(!wildcard_match("*:FF", "AA:FE"));
+        assert!(wildcard_match("AA*FF", "AAFF"));
+        assert!(wildcard_match("A*B*C", "AXXBYYC"));
+        assert!(!wildcard_match("A*B*C", "AXXCYYB"));
+        assert!(!wildcard_match("AA", "AA:BB"));
+    }
+
+    #[test]
+    fn parses_ips_and_ranges() {
+        assert_eq!(matches("192.168.1.20", ip("192.168.1.20")), Some(true));
+        assert_eq!(matches("192.168.1.20", ip("192.168.1.21")), Some(false));
+        assert_eq!(matches("10.0.0.0/8", ip("10.200.3.4")), Some(true));
+        assert_eq!(matches("fe80::/10", ip("fe80::1")), Some(true));
+        // IPv4 peers on dual-stack sockets show up as mapped IPv6 addresses
+        assert_eq!(
+            matches("192.168.1.0/24", ip("::ffff:192.168.1.5")),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn parses_bluetooth_patterns() {
+        let peer = PeerAddr::Bluetooth("aa:bb:cc:dd:ee:ff");
+        assert_eq!(matches("AA-BB-CC-DD-EE-FF", peer), Some(true));
+        assert_eq!(matches("aa:bb:*", peer), Some(true));
+        assert_eq!(matches("11:*", peer), Some(false));
+        assert_eq!(matches("10.0.0.0/8", peer), None);
+        assert_eq!(matches("AA:*", ip("10.0.0.1")), None);
+    }
+
+    #[test]
+    fn rejects_garbage_entries() {
+        assert!(Rule::parse("phone").is_err());
+        assert!(Rule::parse("10.0.0.0/33").is_err());
+        assert!(Rule::parse("").is_err());
+    }
+
+    #[test]
+    fn allowlist_only_covers_its_kind_of_address() {
+        let args = Args {
+            allowlist: vec![String::from("10.0.0.0/8")],
+            blocklist: vec![String::from("10.0.0.66")],
+            ..Args::default()
+        };
+        assert!(check_access(&args, ip("10.0.0.1")).is_ok());
+        assert!(check_access(&args, ip("10.0.0.66")).is_err());
+        assert!(check_access(&args, ip("192.168.1.1")).is_err());
+        assert!(check_access(&args, PeerAddr::Bluetooth("AA:BB:CC:DD:EE:FF")).is_ok());
+    }
+}
@@ -0,0 +1,126 @@
+#[cfg(feature = "ui")]
+use std::{sync::Mutex, time::Duration};
+
+use anyhow::anyhow;
+use log::info;
+#[cfg(feature = "ui")]
+use log::warn;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "ui")]
+use tokio::sync::{mpsc, oneshot};
+
+use super::{Args, SettingsManager};
+
+/// Whether a peer may get a controller, either remembered for it or used
+/// when nobody answers the approval prompt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+pub enum PeerPolicy {
+    #[default]
+    Allow,
+    Deny,
+}
+
+/// The answer to an approval prompt
+#[cfg(feature = "ui")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    AllowOnce,
+    AllowAlways,
+    Deny,
+}
+
+/// A new peer waiting for someone to let it in
+#[cfg(feature = "ui")]
+pub struct ApprovalRequest {
+    pub peer_name: String,
+    reply: oneshot::Sender<Decision>,
+}
+
+#[cfg(feature = "ui")]
+impl ApprovalRequest {
+    pub fn answer(self, decision: Decision) {
+        let _ = self.reply.send(decision);
+    }
+
+    /// The connection gave up waiting, or went away
+    pub fn is_expired(&self) -> bool {
+        self.reply.is_closed()
+    }
+}
+
+/// Where approval prompts go, set once the UI is up
+#[cfg(feature = "ui")]
+static PROMPTS: Mutex<Option<mpsc::UnboundedSender<ApprovalRequest>>> = Mutex::new(None);
+
+#[cfg(feature = "ui")]
+pub fn set_prompt_handler(sender: mpsc::UnboundedSender<ApprovalRequest>) {
+    if let Ok(mut prompts) = PROMPTS.lock() {
+        *prompts = Some(sender);
+    }
+}
+
+/// Lets a peer through when approval isn't required or the peer is allowed,
+/// asking in the UI about peers without a remembered policy
+pub async fn approve(peer_name: &str, peer_id: &str, args: &Args) -> anyhow::Result<()> {
+    if !args.require_approval {
+        return Ok(());
+    }
+
+    let policy = match SettingsManager::peer_policy(peer_id) {
+        Some(policy) => policy,
+        None => prompt(peer_name, peer_id, args).await.unwrap_or_else(|| {
+            info!(
+                "No approval for {peer_name}, using the default {:?}",
+                args.approval_default
+            );
+            args.approval_default
+        }),
+    };
+
+    match policy {
+        PeerPolicy::Allow => Ok(()),
+        PeerPolicy::Deny => Err(anyhow!("Denied connection from {peer_name}")),
+    }
+}
+
+/// Asks the UI about the peer and remembers the answer unless it only
+/// allows this one connection. None when nobody answered in time
+#[cfg(feature = "ui")]
+async fn prompt(peer_name: &str, peer_id: &str, args: &Args) -> Option<PeerPolicy> {
+    let (reply, answer) = oneshot::channel();
+    let request = ApprovalRequest {
+        peer_name: peer_name.to_string(),
+        reply,
+    };
+    let sent = PROMPTS
+        .lock()
+        .ok()
+        .and_then(|prompts| {
+            prompts
+                .as_ref()
+                .map(|prompts| prompts.send(request).is_ok())
+        })
+        .unwrap_or(false);
+    if !sent {
+        return None;
+    }
+
+    info!("Waiting for approval of {peer_name}");
+    let timeout = Duration::from_millis(args.approval_timeout_ms);
+    let decision = tokio::time::timeout(timeout, answer).await.ok()?.ok()?;
+    let policy = match decision {
+        Decision::AllowOnce => return Some(PeerPolicy::Allow),
+        Decision::AllowAlways => PeerPolicy::Allow,
+        Decision::Deny => PeerPolicy::Deny,
+    };
+    if let Err(err) = SettingsManager::save_peer_policy(peer_id, policy) {
+        warn!("Failed to remember {policy:?} for {peer_id}: {err}");
+    }
+    Some(policy)
+}
+
+/// Without the UI there's nobody to ask
+#[cfg(not(feature = "ui"))]
+async fn prompt(_peer_name: &str, _peer_id: &str, _args: &Args) -> Option<PeerPolicy> {
+    None
+}
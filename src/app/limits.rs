@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// What happens to a connection sending faster than `--max-message-rate`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+pub enum RateLimitAction {
+    /// Holds messages back until they fit the rate again. Stream transports
+    /// lose none, UDP and MQTT drop what doesn't fit their per-peer queue
+    #[default]
+    Throttle,
+    Disconnect,
+}
+
+/// Token bucket allowing `rate` messages per second, with bursts of as many
+pub struct RateLimiter {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// None when `rate` is 0, which means no limit
+    pub fn new(rate: u32) -> Option<Self> {
+        (rate > 0).then(|| Self {
+            rate: rate.into(),
+            tokens: rate.into(),
+            last_refill: Instant::now(),
+        })
+    }
+
+    /// Takes a token for the next message and returns how long it has to
+    /// wait to stay within the rate, zero when it doesn't
+    pub fn take(&mut self) -> Duration {
+        let now = Instant::now();
+        let refill = now.duration_since(self.last_refill).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(self.rate) - 1.0;
+        self.last_refill = now;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pretends `elapsed` passed since the last message
+    fn wait(limiter: &mut RateLimiter, elapsed: Duration) {
+        limiter.last_refill -= elapsed;
+    }
+
+    #[test]
+    fn zero_means_no_limit() {
+        assert!(RateLimiter::new(0).is_none());
+    }
+
+    #[test]
+    fn allows_a_burst_of_rate_messages() {
+        let mut limiter = RateLimiter::new(10).unwrap();
+        for _ in 0..10 {
+            assert!(limiter.take().is_zero());
+        }
+        let delay = limiter.take();
+        assert!(delay > Duration::from_millis(90) && delay <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn delays_grow_while_over_the_rate() {
+        let mut limiter = RateLimiter::new(10).unwrap();
+        for _ in 0..10 {
+            limiter.take();
+        }
+        let first = limiter.take();
+        let second = limiter.take();
+        assert!(second > first + Duration::from_millis(90));
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let mut limiter = RateLimiter::new(10).unwrap();
+        for _ in 0..10 {
+            limiter.take();
+        }
+        wait(&mut limiter, Duration::from_millis(250));
+        assert!(limiter.take().is_zero());
+        assert!(limiter.take().is_zero());
+        assert!(!limiter.take().is_zero());
+    }
+
+    #[test]
+    fn refill_is_capped_at_one_burst() {
+        let mut limiter = RateLimiter::new(5).unwrap();
+        wait(&mut limiter, Duration::from_secs(60));
+        for _ in 0..5 {
+            assert!(limiter.take().is_zero());
+        }
+        assert!(!limiter.take().is_zero());
+    }
+}
@@ -1,33 +1,87 @@
 #![allow(clippy::derivable_impls)]
+mod access;
+mod approval;
+mod limits;
+mod pairing;
+mod parse_errors;
+mod peers;
+#[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+mod qr;
 mod runtime;
+mod sessions;
+mod settings;
+mod slots;
 
 use anyhow::anyhow;
-use log::{error, info};
-#[cfg(feature = "ui")]
-use log::warn;
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, time::Instant};
-#[cfg(feature = "ui")]
-use std::{fs, path::PathBuf};
+#[cfg(any(feature = "tls", all(unix, feature = "unix")))]
+use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
+#[cfg(feature = "ui")]
+pub use access::block_address;
+#[cfg(any(
+    feature = "ws",
+    feature = "bluetooth",
+    feature = "tcp",
+    feature = "udp"
+))]
+pub use access::{PeerAddr, check_access};
+pub use approval::PeerPolicy;
+#[cfg(feature = "ui")]
+pub use approval::{ApprovalRequest, Decision, set_prompt_handler};
 use clap::Parser;
+pub use limits::RateLimitAction;
+use limits::RateLimiter;
+pub use pairing::pairing_pin;
+pub use parse_errors::ParseErrorPolicy;
+use parse_errors::ParseErrors;
 #[cfg(feature = "ui")]
-use gpui_component::ThemeMode;
+pub use peers::{PeerInfo, connected_peers, disconnect_peer, watch_peers};
+#[cfg(all(
+    feature = "qr",
+    feature = "ui",
+    any(feature = "ws", feature = "tcp", feature = "udp")
+))]
+pub use qr::{QrModules, connection_info};
 pub use runtime::RuntimeTransport;
+use sessions::Session;
+pub use settings::SettingsManager;
+use slots::PlayerSlot;
 #[cfg(feature = "ui")]
 use tokio::sync::watch;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::{
-    controller::{Controller, KeyState, Options as ControllerOptions},
-    input::{Key, KeyEvent, Message},
+    controller::{
+        AxisPolicy, Controller, KeyState, Options as ControllerOptions, backend_capabilities,
+    },
+    input::{InvalidMessage, KeyEvent, Message, mapping},
     transport::TransportConnection,
 };
 
 #[derive(Clone, Debug, Parser, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Args {
     #[arg(short, long, default_value_t = Args::default_port())]
     pub port: u16,
+    /// Address or network interface to listen on. Repeat for several. Accepts IPv4, IPv6 (`::`
+    /// is dual-stack), `127.0.0.1` for localhost only, or an interface name such as `eth0`
+    #[arg(long, default_values_t = [String::from(Args::default_bind())])]
+    pub bind: Vec<String>,
+    /// Sets the port used by the raw TCP transport
+    #[arg(long, default_value_t = Args::default_tcp_port())]
+    pub tcp_port: u16,
+    /// Sets the port used by the UDP transport
+    #[arg(long, default_value_t = Args::default_udp_port())]
+    pub udp_port: u16,
+    /// Closes a UDP peer's controller after this many milliseconds without a datagram from it
+    #[arg(long, default_value_t = Args::default_udp_timeout_ms())]
+    pub udp_timeout_ms: u64,
     //TODO: test for a good default
     /// Decides what amount of time can pass between clicks to hold (-1 to disable)
     #[arg(long, default_value_t = Args::default_double_tap_timing())]
@@ -40,15 +94,169 @@ pub struct Args {
     #[command(flatten)]
     pub controller: ControllerOptions,
 
-    #[arg(long, value_enum, default_value_t = TransportKind::default())]
-    pub transport: TransportKind,
+    /// Merges every connection into one virtual controller with this name instead of creating one
+    /// per connection
+    #[arg(long)]
+    pub shared_device: Option<String>,
+
+    /// Decides how axis values from several connections on a shared device are combined
+    #[arg(long, value_enum, default_value_t = AxisPolicy::default())]
+    pub shared_axis_policy: AxisPolicy,
+
+    /// Sets the number of player slots. Known phones always get the slot they had before
+    #[arg(long, default_value_t = Args::default_max_players())]
+    pub max_players: u8,
+
+    /// Keeps a disconnected phone's controller, with inputs released, for this many milliseconds
+    /// so a reconnect gets it back (0 to disable)
+    #[arg(long, default_value_t = 0)]
+    pub resume_grace_ms: u64,
+
+    /// Ends a connection after this many milliseconds without a message from it, releasing all of
+    /// its inputs (0 to disable)
+    #[arg(long, default_value_t = 0)]
+    pub idle_timeout_ms: u64,
+    /// Pings WebSocket clients this often, in milliseconds (0 to disable)
+    #[arg(long, default_value_t = Args::default_heartbeat_interval_ms())]
+    pub heartbeat_interval_ms: u64,
+    /// Ends a WebSocket connection once nothing, not even a pong, arrived for this many
+    /// milliseconds, releasing all of its inputs
+    #[arg(long, default_value_t = Args::default_heartbeat_timeout_ms())]
+    pub heartbeat_timeout_ms: u64,
+
+    /// Drops a connection that hasn't finished its handshake after this many milliseconds
+    #[arg(long, default_value_t = Args::default_handshake_timeout_ms())]
+    pub handshake_timeout_ms: u64,
+    /// Stops accepting new connections on a transport while this many handshakes are pending
+    #[arg(long, default_value_t = Args::default_max_pending_handshakes())]
+    pub max_pending_handshakes: usize,
+
+    /// Rejects new connections once this many are open across all transports (0 for no limit)
+    #[arg(long, default_value_t = 0)]
+    pub max_connections: usize,
+    /// Rejects new connections on a transport once it has this many open (0 for no limit)
+    #[arg(long, default_value_t = 0)]
+    pub max_connections_per_transport: usize,
+    /// Limits every connection to this many messages per second (0 for no limit)
+    #[arg(long, default_value_t = 0)]
+    pub max_message_rate: u32,
+    /// What happens to a connection sending faster than `--max-message-rate`
+    #[arg(long, value_enum, default_value_t = RateLimitAction::default())]
+    pub rate_limit_action: RateLimitAction,
+    /// What happens to a connection sending messages that can't be understood
+    #[arg(long, value_enum, default_value_t = ParseErrorPolicy::default())]
+    pub parse_error_policy: ParseErrorPolicy,
+    /// Invalid messages a connection may send with `--parse-error-policy skip-with-limit`
+    #[arg(long, default_value_t = Args::default_max_parse_errors())]
+    pub max_parse_errors: u64,
+
+    /// Requires clients to present the pairing PIN before they get a controller. Devices that
+    /// paired once are remembered
+    #[arg(long, default_value_t = false)]
+    pub require_pairing: bool,
+    /// Fixed pairing PIN or token. Without it a random 6 digit PIN is generated on every start
+    #[arg(long, requires = "require_pairing")]
+    pub pairing_pin: Option<String>,
+
+    /// Asks in the window before a new phone gets a controller. Answers to allow or deny a phone
+    /// for good are remembered
+    #[arg(long, default_value_t = false)]
+    pub require_approval: bool,
+    /// What happens to a phone nobody approves in time, and to every new phone without the UI
+    #[arg(long, value_enum, default_value_t = PeerPolicy::default())]
+    pub approval_default: PeerPolicy,
+    /// Gives up waiting for an approval after this many milliseconds
+    #[arg(long, default_value_t = Args::default_approval_timeout_ms())]
+    pub approval_timeout_ms: u64,
+
+    /// Only lets in these peers: IP addresses, CIDR ranges such as `192.168.1.0/24`, or
+    /// Bluetooth addresses where `*` matches anything. Repeat for several. Peers of a kind the
+    /// list has no entry for aren't restricted
+    #[arg(long = "allow", value_name = "PEER")]
+    pub allowlist: Vec<String>,
+    /// Rejects these peers, in the same format as `--allow`. Repeat for several
+    #[arg(long = "block", value_name = "PEER")]
+    pub blocklist: Vec<String>,
+
+    /// Transport to serve. Repeat to serve several at once
+    #[arg(
+        long = "transport",
+        value_name = "TRANSPORT",
+        value_enum,
+        default_values_t = [TransportKind::default()]
+    )]
+    #[serde(alias = "transport", deserialize_with = "one_or_many")]
+    pub transports: Vec<TransportKind>,
 
     #[cfg(all(feature = "bluetooth", target_os = "linux"))]
     #[arg(long, default_value_t = Args::default_bt_channel())]
     pub bt_channel: u8,
 
+    /// Serves the WebSocket transport over TLS (wss://)
+    #[cfg(feature = "tls")]
+    #[arg(long, default_value_t = false)]
+    pub tls: bool,
+    /// PEM certificate chain for TLS. Without it a self-signed certificate is generated and kept
+    /// in the config dir
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key for `--tls-cert`
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Host of the MQTT broker to subscribe to
+    #[cfg(feature = "mqtt")]
+    #[arg(long, default_value_t = String::from(Args::default_mqtt_host()))]
+    pub mqtt_host: String,
+    #[cfg(feature = "mqtt")]
+    #[arg(long, default_value_t = Args::default_mqtt_port())]
+    pub mqtt_port: u16,
+    /// Topic filter to subscribe to. The level matched by the first `+` (or a trailing `#`)
+    /// tells the publishing phones apart
+    #[cfg(feature = "mqtt")]
+    #[arg(long, default_value_t = String::from(Args::default_mqtt_topic()))]
+    pub mqtt_topic: String,
+    #[cfg(feature = "mqtt")]
+    #[arg(long)]
+    pub mqtt_username: Option<String>,
+    #[cfg(feature = "mqtt")]
+    #[arg(long)]
+    pub mqtt_password: Option<String>,
+    /// Removes an MQTT phone's controller after this many milliseconds without a message from it
+    #[cfg(feature = "mqtt")]
+    #[arg(long, default_value_t = Args::default_mqtt_timeout_ms())]
+    pub mqtt_timeout_ms: u64,
+
+    /// Path of the Unix socket to listen on. Defaults to `droidpad-gamepad.sock` in
+    /// `$XDG_RUNTIME_DIR`
+    #[cfg(all(unix, feature = "unix"))]
+    #[arg(long)]
+    pub unix_socket: Option<PathBuf>,
+    /// Octal file mode of the Unix socket, which decides who may connect to it
+    #[cfg(all(unix, feature = "unix"))]
+    #[arg(long, default_value_t = String::from(Args::default_unix_socket_mode()))]
+    pub unix_socket_mode: String,
+
+    /// Character device to read, such as `/dev/rfcomm0`, a USB serial adapter or a pty
+    #[cfg(feature = "serial")]
+    #[arg(long, default_value_t = String::from(Args::default_serial_device()))]
+    pub serial_device: String,
+    #[cfg(feature = "serial")]
+    #[arg(long, default_value_t = Args::default_serial_baud_rate())]
+    pub serial_baud_rate: u32,
+    /// Milliseconds between attempts to open the serial device while it's missing
+    #[cfg(feature = "serial")]
+    #[arg(long, default_value_t = Args::default_serial_reopen_ms())]
+    pub serial_reopen_ms: u64,
+
     #[arg(long, default_value_t = false)]
     pub disable_tray: bool,
+    /// Stops announcing the WebSocket, TCP and UDP transports on the local network over mDNS
+    #[cfg(feature = "mdns")]
+    #[arg(long, default_value_t = false)]
+    pub disable_mdns: bool,
 }
 
 impl Args {
@@ -56,6 +264,23 @@ impl Args {
         1715
     }
 
+    pub const fn default_bind() -> &'static str {
+        "0.0.0.0"
+    }
+
+    pub const fn default_tcp_port() -> u16 {
+        1716
+    }
+
+    pub const fn default_udp_port() -> u16 {
+        1715
+    }
+
+    /// Long, since a phone held still sends nothing at all
+    pub const fn default_udp_timeout_ms() -> u64 {
+        300_000
+    }
+
     pub const fn default_double_tap_timing() -> i64 {
         200
     }
@@ -64,33 +289,187 @@ impl Args {
         "_dth"
     }
 
+    pub const fn default_max_players() -> u8 {
+        4
+    }
+
+    pub const fn default_max_parse_errors() -> u64 {
+        100
+    }
+
+    pub const fn default_heartbeat_interval_ms() -> u64 {
+        5000
+    }
+
+    pub const fn default_heartbeat_timeout_ms() -> u64 {
+        15000
+    }
+
+    pub const fn default_handshake_timeout_ms() -> u64 {
+        10000
+    }
+
+    pub const fn default_max_pending_handshakes() -> usize {
+        16
+    }
+
+    pub const fn default_approval_timeout_ms() -> u64 {
+        30000
+    }
+
     #[cfg(all(feature = "bluetooth", target_os = "linux"))]
     pub const fn default_bt_channel() -> u8 {
         3
     }
+
+    #[cfg(feature = "mqtt")]
+    pub const fn default_mqtt_host() -> &'static str {
+        "localhost"
+    }
+
+    #[cfg(feature = "mqtt")]
+    pub const fn default_mqtt_port() -> u16 {
+        1883
+    }
+
+    #[cfg(feature = "mqtt")]
+    pub const fn default_mqtt_topic() -> &'static str {
+        "droidpad/+"
+    }
+
+    #[cfg(feature = "mqtt")]
+    pub const fn default_mqtt_timeout_ms() -> u64 {
+        300_000
+    }
+
+    #[cfg(all(unix, feature = "unix"))]
+    pub const fn default_unix_socket_mode() -> &'static str {
+        "600"
+    }
+
+    #[cfg(feature = "serial")]
+    pub const fn default_serial_device() -> &'static str {
+        "/dev/rfcomm0"
+    }
+
+    #[cfg(feature = "serial")]
+    pub const fn default_serial_baud_rate() -> u32 {
+        115200
+    }
+
+    #[cfg(feature = "serial")]
+    pub const fn default_serial_reopen_ms() -> u64 {
+        1000
+    }
 }
 
 impl Default for Args {
     fn default() -> Self {
         Self {
             port: Self::default_port(),
+            bind: vec![String::from(Self::default_bind())],
+            tcp_port: Self::default_tcp_port(),
+            udp_port: Self::default_udp_port(),
+            udp_timeout_ms: Self::default_udp_timeout_ms(),
             double_tap_timing: Self::default_double_tap_timing(),
             double_tap_postfix: String::from(Self::default_double_tap_postfix()),
             controller: ControllerOptions::default(),
-            transport: TransportKind::default(),
+            shared_device: None,
+            shared_axis_policy: AxisPolicy::default(),
+            max_players: Self::default_max_players(),
+            resume_grace_ms: 0,
+            idle_timeout_ms: 0,
+            heartbeat_interval_ms: Self::default_heartbeat_interval_ms(),
+            heartbeat_timeout_ms: Self::default_heartbeat_timeout_ms(),
+            handshake_timeout_ms: Self::default_handshake_timeout_ms(),
+            max_pending_handshakes: Self::default_max_pending_handshakes(),
+            max_connections: 0,
+            max_connections_per_transport: 0,
+            max_message_rate: 0,
+            rate_limit_action: RateLimitAction::default(),
+            parse_error_policy: ParseErrorPolicy::default(),
+            max_parse_errors: Self::default_max_parse_errors(),
+            require_pairing: false,
+            pairing_pin: None,
+            require_approval: false,
+            approval_default: PeerPolicy::default(),
+            approval_timeout_ms: Self::default_approval_timeout_ms(),
+            allowlist: Vec::new(),
+            blocklist: Vec::new(),
+            transports: vec![TransportKind::default()],
             #[cfg(all(feature = "bluetooth", target_os = "linux"))]
             bt_channel: Self::default_bt_channel(),
+            #[cfg(feature = "tls")]
+            tls: false,
+            #[cfg(feature = "tls")]
+            tls_cert: None,
+            #[cfg(feature = "tls")]
+            tls_key: None,
+            #[cfg(feature = "mqtt")]
+            mqtt_host: String::from(Self::default_mqtt_host()),
+            #[cfg(feature = "mqtt")]
+            mqtt_port: Self::default_mqtt_port(),
+            #[cfg(feature = "mqtt")]
+            mqtt_topic: String::from(Self::default_mqtt_topic()),
+            #[cfg(feature = "mqtt")]
+            mqtt_username: None,
+            #[cfg(feature = "mqtt")]
+            mqtt_password: None,
+            #[cfg(feature = "mqtt")]
+            mqtt_timeout_ms: Self::default_mqtt_timeout_ms(),
+            #[cfg(all(unix, feature = "unix"))]
+            unix_socket: None,
+            #[cfg(all(unix, feature = "unix"))]
+            unix_socket_mode: String::from(Self::default_unix_socket_mode()),
+            #[cfg(feature = "serial")]
+            serial_device: String::from(Self::default_serial_device()),
+            #[cfg(feature = "serial")]
+            serial_baud_rate: Self::default_serial_baud_rate(),
+            #[cfg(feature = "serial")]
+            serial_reopen_ms: Self::default_serial_reopen_ms(),
             disable_tray: false,
+            #[cfg(feature = "mdns")]
+            disable_mdns: false,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, clap::ValueEnum, Serialize, Deserialize, PartialEq, Eq)]
+/// Accepts both a single transport, as older settings files store it, and a list
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<TransportKind>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(TransportKind),
+        Many(Vec<TransportKind>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(kind) => vec![kind],
+        OneOrMany::Many(kinds) => kinds,
+    })
+}
+
+#[derive(
+    Clone, Copy, Debug, clap::ValueEnum, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord,
+)]
 pub enum TransportKind {
     #[cfg(feature = "ws")]
     Ws,
     #[cfg(feature = "bluetooth")]
     Bluetooth,
+    #[cfg(feature = "tcp")]
+    Tcp,
+    #[cfg(feature = "udp")]
+    Udp,
+    #[cfg(feature = "mqtt")]
+    Mqtt,
+    #[cfg(all(unix, feature = "unix"))]
+    Unix,
+    #[cfg(feature = "serial")]
+    Serial,
 }
 
 #[cfg(feature = "ws")]
@@ -107,74 +486,64 @@ impl Default for TransportKind {
     }
 }
 
-#[cfg(feature = "ui")]
-pub struct SettingsManager;
-
-#[cfg(feature = "ui")]
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct StoredSettings {
-    #[serde(flatten)]
-    args: Args,
-    #[serde(default)]
-    theme_mode: Option<ThemeMode>,
-}
-
-#[cfg(feature = "ui")]
-impl SettingsManager {
-    fn config_path() -> PathBuf {
-        let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
-        path.push("droidpad-gamepad");
-        path.push("config.json");
-        path
-    }
-
-    fn load_stored() -> StoredSettings {
-        let path = Self::config_path();
-        if path.exists() {
-            match fs::read_to_string(&path) {
-                Ok(content) => match serde_json::from_str::<StoredSettings>(&content) {
-                    Ok(settings) => return settings,
-                    Err(err) => warn!("Failed to parse config file at {path:?}: {err}"),
-                },
-                Err(err) => warn!("Failed to read config file at {path:?}: {err}"),
-            }
-        }
-
-        StoredSettings {
-            args: Args::default(),
-            theme_mode: None,
-        }
+#[cfg(all(not(feature = "ws"), not(feature = "bluetooth"), feature = "tcp"))]
+impl Default for TransportKind {
+    fn default() -> Self {
+        Self::Tcp
     }
+}
 
-    fn save_stored(settings: &StoredSettings) -> anyhow::Result<()> {
-        let path = Self::config_path();
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        let content = serde_json::to_string_pretty(settings)?;
-        fs::write(path, content)?;
-        Ok(())
+#[cfg(all(
+    not(feature = "ws"),
+    not(feature = "bluetooth"),
+    not(feature = "tcp"),
+    feature = "udp"
+))]
+impl Default for TransportKind {
+    fn default() -> Self {
+        Self::Udp
     }
+}
 
-    pub fn save(args: &Args) -> anyhow::Result<()> {
-        let mut settings = Self::load_stored();
-        settings.args = args.clone();
-        Self::save_stored(&settings)
+#[cfg(all(
+    not(feature = "ws"),
+    not(feature = "bluetooth"),
+    not(feature = "tcp"),
+    not(feature = "udp"),
+    feature = "mqtt"
+))]
+impl Default for TransportKind {
+    fn default() -> Self {
+        Self::Mqtt
     }
+}
 
-    pub fn load_ui_settings(default_theme_mode: ThemeMode) -> (Args, Option<ThemeMode>) {
-        let settings = Self::load_stored();
-        let theme_mode = settings.theme_mode.or(Some(default_theme_mode));
-        (
-            settings.args,
-            theme_mode.filter(|_| settings.theme_mode.is_some()),
-        )
+#[cfg(all(
+    not(feature = "ws"),
+    not(feature = "bluetooth"),
+    not(feature = "tcp"),
+    not(feature = "udp"),
+    not(feature = "mqtt"),
+    all(unix, feature = "unix")
+))]
+impl Default for TransportKind {
+    fn default() -> Self {
+        Self::Unix
     }
+}
 
-    pub fn save_theme_mode(theme_mode: ThemeMode) -> anyhow::Result<()> {
-        let mut settings = Self::load_stored();
-        settings.theme_mode = Some(theme_mode);
-        Self::save_stored(&settings)
+#[cfg(all(
+    not(feature = "ws"),
+    not(feature = "bluetooth"),
+    not(feature = "tcp"),
+    not(feature = "udp"),
+    not(feature = "mqtt"),
+    not(all(unix, feature = "unix")),
+    feature = "serial"
+))]
+impl Default for TransportKind {
+    fn default() -> Self {
+        Self::Serial
     }
 }
 
@@ -188,18 +557,29 @@ pub fn init_logging() {
         .try_init();
 }
 
-pub(crate) async fn start_transport(args: &Args) -> anyhow::Result<RuntimeTransport> {
+pub(crate) async fn start_transport(
+    args: &Args,
+    kind: TransportKind,
+) -> anyhow::Result<RuntimeTransport> {
     args.controller.initialize()?;
+    access::validate_access_lists(args)?;
+    if let Some(capabilities) = backend_capabilities(args.controller.backend) {
+        info!(
+            "Controller backend {:?} capabilities: {capabilities}",
+            args.controller.backend
+        );
+    }
 
-    let mut transport = RuntimeTransport::new(args.transport);
-    info!("Listening on transport {:?}", args.transport);
+    let mut transport = RuntimeTransport::new(kind);
+    info!("Listening on transport {kind:?}");
     transport.listen(args.clone()).await?;
-    info!("Transport {:?} started successfully", args.transport);
+    info!("Transport {kind:?} started successfully");
     Ok(transport)
 }
 
 pub(crate) async fn serve_transport_loop(
     mut transport: RuntimeTransport,
+    kind: TransportKind,
     args: Args,
     #[cfg(feature = "ui")] mut shutdown: Option<watch::Receiver<bool>>,
 ) -> anyhow::Result<()> {
@@ -214,7 +594,7 @@ pub(crate) async fn serve_transport_loop(
                 changed = shutdown.changed() => {
                     match changed {
                         Ok(()) if *shutdown.borrow() => {
-                            info!("Stopping transport service for {:?}", args.transport);
+                            info!("Stopping transport service for {kind:?}");
                             return Ok(());
                         }
                         Ok(()) => {}
@@ -226,13 +606,13 @@ pub(crate) async fn serve_transport_loop(
                         Ok(connection) => {
                             let args = args.clone();
                             tokio::spawn(async move {
-                                if let Err(err) = handle_connection(connection, args).await {
+                                if let Err(err) = handle_connection(connection, kind, args).await {
                                     error!("{err}");
                                 }
                             });
                         }
                         Err(err) => {
-                            error!("Failed to accept transport connection: {err}");
+                            error!("Failed to accept {kind:?} connection: {err}");
                         }
                     }
                 }
@@ -244,13 +624,13 @@ pub(crate) async fn serve_transport_loop(
             Ok(connection) => {
                 let args = args.clone();
                 tokio::spawn(async move {
-                    if let Err(err) = handle_connection(connection, args).await {
+                    if let Err(err) = handle_connection(connection, kind, args).await {
                         error!("{err}");
                     }
                 });
             }
             Err(err) => {
-                error!("Failed to accept transport connection: {err}");
+                error!("Failed to accept {kind:?} connection: {err}");
             }
         }
     }
@@ -259,47 +639,244 @@ pub(crate) async fn serve_transport_loop(
 #[cfg(not(feature = "ui"))]
 pub async fn run_cli(args: Args) {
     init_logging();
+    if let Err(err) = crate::controller::register_builtin_backends() {
+        error!("{err}");
+        return;
+    }
 
-    let transport = match start_transport(&args).await {
-        Ok(t) => t,
-        Err(err) => {
-            error!("{err}");
-            return;
-        }
-    };
+    let mut kinds = args.transports.clone();
+    kinds.sort();
+    kinds.dedup();
 
-    if let Err(err) = serve_transport_loop(transport, args).await {
-        error!("{err}");
+    if let Some(pin) = pairing_pin(&args) {
+        info!("Pairing PIN: {pin}");
+    }
+
+    // A transport that fails to start doesn't keep the others from serving
+    let mut services = tokio::task::JoinSet::new();
+    let mut started = Vec::new();
+    for kind in kinds {
+        let transport = match start_transport(&args, kind).await {
+            Ok(t) => t,
+            Err(err) => {
+                error!("Failed to start transport {kind:?}: {err}");
+                continue;
+            }
+        };
+
+        started.push(kind);
+
+        let args = args.clone();
+        services.spawn(async move {
+            if let Err(err) = serve_transport_loop(transport, kind, args).await {
+                error!("Transport {kind:?} stopped: {err}");
+            }
+        });
+    }
+
+    if services.is_empty() {
+        error!("No transport could be started");
+        return;
+    }
+    #[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+    print_connection_qr(&args, started);
+    services.join_all().await;
+}
+
+/// Prints a QR code phones can scan to connect, for the first transport that
+/// listens on the network
+#[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+fn print_connection_qr(args: &Args, kinds: Vec<TransportKind>) {
+    let Some(info) = qr::connection_info(args, kinds) else {
+        return;
+    };
+    match qr::render_text(&info) {
+        Ok(code) => {
+            info!("Scan to connect:");
+            println!("{code}");
+        }
+        Err(err) => warn!("Failed to render the connection QR code: {err}"),
     }
 }
 
-pub async fn handle_connection<C>(mut connection: C, args: Args) -> anyhow::Result<()>
+/// Lists the peer and gets it through pairing and approval, then hands it
+/// the controller it left behind or a new one
+async fn admit<C>(
+    connection: &mut C,
+    kind: TransportKind,
+    args: &Args,
+) -> anyhow::Result<(peers::Registration, Session)>
+where
+    C: TransportConnection,
+{
+    let name = connection.peer_name();
+    let peer_id = connection.peer_id();
+    // Registered first, so peers still pairing count against the limits
+    let registration = peers::register(kind, &name, connection.peer_address(), args)?;
+    pairing::authenticate(connection, args).await?;
+
+    let session = match sessions::resume(&peer_id) {
+        Some(session) => {
+            info!("Controller reconnected: {name}");
+            session
+        }
+        None => {
+            approval::approve(&name, &peer_id, args).await?;
+            new_session(&name, &connection.slot_hint(), args)?
+        }
+    };
+    Ok((registration, session))
+}
+
+pub async fn handle_connection<C>(
+    mut connection: C,
+    kind: TransportKind,
+    args: Args,
+) -> anyhow::Result<()>
 where
     C: TransportConnection + Send + 'static,
 {
     let name = connection.peer_name();
+    let peer_id = connection.peer_id();
+    let (registration, mut session) = match admit(&mut connection, kind, &args).await {
+        Ok(admitted) => admitted,
+        Err(err) => {
+            connection.reject();
+            return Err(err);
+        }
+    };
+
+    let mut keys_state: HashMap<u8, KeyState> = HashMap::new();
+    let mut double_tap_state: HashMap<u8, Instant> = HashMap::new();
+    let mut rate_limiter = RateLimiter::new(args.max_message_rate);
+    let mut parse_errors = ParseErrors::new(&args);
+    let idle_timeout = Duration::from_millis(args.idle_timeout_ms);
+    // Ok(true) when the peer was disconnected from the window
+    let result = async {
+        loop {
+            let message = tokio::select! {
+                message = connection.recv_message() => message,
+                () = registration.disconnected() => return Ok(true),
+                () = tokio::time::sleep(idle_timeout), if args.idle_timeout_ms > 0 => {
+                    return Err(anyhow!(
+                        "No message from {name} for {}ms, treating the link as dead",
+                        args.idle_timeout_ms
+                    ));
+                }
+            };
+            let message = match message {
+                Ok(Some(message)) => message,
+                Ok(None) => return Ok(false),
+                Err(err) => {
+                    parse_errors.tolerate(err, &name)?;
+                    registration.count_invalid();
+                    continue;
+                }
+            };
 
-    match Controller::new(&name, &args.controller) {
-        Ok(mut controller) => {
-            info!("New controller connected: {name}");
-            let mut keys_state: HashMap<u8, KeyState> = HashMap::new();
-            let mut double_tap_state: HashMap<u8, Instant> = HashMap::new();
-
-            while let Some(message) = connection.recv_message().await? {
-                handle_message(
-                    message,
-                    &mut controller,
-                    &mut keys_state,
-                    &mut double_tap_state,
-                    &args,
-                )
-                .await?;
+            registration.count_message();
+            if let Some(delay) = rate_limiter.as_mut().map(RateLimiter::take)
+                && !delay.is_zero()
+            {
+                if args.rate_limit_action == RateLimitAction::Disconnect {
+                    return Err(anyhow!(
+                        "Disconnected {name}: over {} messages per second",
+                        args.max_message_rate
+                    ));
+                }
+                if registration.count_throttled() == 0 {
+                    warn!(
+                        "Throttling {name}, it sends over {} messages per second",
+                        args.max_message_rate
+                    );
+                }
+                tokio::select! {
+                    () = tokio::time::sleep(delay) => {}
+                    () = registration.disconnected() => return Ok(true),
+                }
             }
 
-            info!("Controller disconnected: {name}");
-            Ok(())
+            if let Err(err) = handle_message(
+                message,
+                &mut session.controller,
+                &mut keys_state,
+                &mut double_tap_state,
+                &args,
+            )
+            .await
+            {
+                parse_errors.tolerate(err, &name)?;
+                registration.count_invalid();
+            }
+        }
+    }
+    .await;
+
+    let mut summary = format!("{} messages", registration.messages());
+    if registration.throttled() > 0 {
+        summary += &format!(", {} throttled", registration.throttled());
+    }
+    if registration.invalid() > 0 {
+        summary += &format!(", {} invalid", registration.invalid());
+    }
+    info!("Controller disconnected: {name} after {summary}");
+    drop(registration);
+    let disconnected_on_request = matches!(result, Ok(true));
+    if args.resume_grace_ms > 0 && !disconnected_on_request {
+        sessions::park(
+            peer_id,
+            session,
+            Duration::from_millis(args.resume_grace_ms),
+        );
+    } else if result.is_err() {
+        // A dead link can end the session mid-press, so nothing stays held
+        // while the device goes away
+        if let Err(err) = session.controller.neutralize() {
+            warn!("Failed to neutralize inputs for {name}: {err}");
+        }
+    }
+
+    result.map(|_| ())
+}
+
+fn new_session(name: &str, slot_hint: &str, args: &Args) -> anyhow::Result<Session> {
+    let session = match &args.shared_device {
+        Some(shared_name) => Session {
+            controller: Controller::shared(shared_name, &args.controller, args.shared_axis_policy)?,
+            player_slot: None,
+        },
+        None => {
+            let slot = PlayerSlot::acquire(slot_hint, args.max_players)?;
+            let device_name = format!("{name} (Player {})", slot.slot());
+            let mut controller = Controller::new(&device_name, &args.controller)?;
+            controller.set_player_index(slot.slot())?;
+            Session {
+                controller,
+                player_slot: Some(slot),
+            }
         }
-        Err(err) => Err(err),
+    };
+
+    match &session.player_slot {
+        Some(slot) => info!("New controller connected: {name} as player {}", slot.slot()),
+        None => info!("New controller connected: {name}"),
+    }
+    warn_unsupported_bindings(&session.controller, name, args);
+
+    Ok(session)
+}
+
+fn warn_unsupported_bindings(controller: &Controller, name: &str, args: &Args) {
+    let unsupported = mapping::BINDINGS
+        .iter()
+        .filter(|key| !controller.capabilities().supports(**key))
+        .collect::<Vec<_>>();
+
+    if !unsupported.is_empty() {
+        warn!(
+            "Backend {:?} can't output {unsupported:?}, these bindings from {name} will be ignored",
+            args.controller.backend
+        );
     }
 }
 
@@ -316,51 +893,27 @@ async fn handle_message(
             button,
             state,
         } => {
-            let input = match button.as_str() {
-                "LEFT" => Key::DPadLeft(state),
-                "RIGHT" => Key::DPadRight(state),
-                "UP" => Key::DPadUp(state),
-                "DOWN" => Key::DPadDown(state),
-                _ => return Err(anyhow!("Unknown DPAD button: {button}")),
-            };
+            let input = mapping::dpad_key(&button, state)
+                .ok_or_else(|| InvalidMessage(anyhow!("Unknown DPAD button: {button}")))?;
 
             device.write_input(input)?;
         }
-        Message::Joystick { id, x, y } => match id.as_str() {
-            "left" => {
-                device.write_input(Key::LeftJoystickX(x))?;
-                device.write_input(Key::LeftJoystickY(y))?;
-            }
-            "right" => {
-                device.write_input(Key::RightJoystickX(x))?;
-                device.write_input(Key::RightJoystickY(y))?;
+        Message::Joystick { id, x, y } => {
+            if let Some(keys) = mapping::joystick_keys(&id, x, y) {
+                for key in keys {
+                    device.write_input(key)?;
+                }
             }
-            _ => {}
-        },
+        }
         Message::Button { id, state } => {
             let button_id = if args.double_tap_postfix.is_empty() {
-                id.as_str()
+                &*id
             } else {
                 id.split_once(&args.double_tap_postfix)
                     .map(|(before, _)| before)
-                    .unwrap_or(id.as_str())
+                    .unwrap_or(&id)
             };
-            let input = match button_id {
-                "A" => Some(Key::A(state)),
-                "B" => Some(Key::B(state)),
-                "X" => Some(Key::X(state)),
-                "Y" => Some(Key::Y(state)),
-                "lb" => Some(Key::BumperLeft(state)),
-                "lt" => Some(Key::TriggerLeft(state)),
-                "rb" => Some(Key::BumperRight(state)),
-                "rt" => Some(Key::TriggerRight(state)),
-                "start" => Some(Key::Start(state)),
-                "back" => Some(Key::Select(state)),
-                "thumb_right" => Some(Key::ThumbRight(state)),
-                "thumb_left" => Some(Key::ThumbLeft(state)),
-                _ => None,
-            };
-            let Some(input) = input else {
+            let Some(input) = mapping::button_key(button_id, state) else {
                 return Ok(());
             };
 
@@ -411,6 +964,10 @@ async fn handle_message(
                 (KeyState::Pressed, KeyEvent::Press) => {}
             }
         }
+        Message::Unknown => {
+            debug!("Ignoring a message of an unknown type");
+            return Ok(());
+        }
     };
     device.synchronize()?;
 
@@ -0,0 +1,163 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use log::{info, warn};
+use rand::Rng;
+
+use super::{Args, SettingsManager};
+use crate::transport::TransportConnection;
+
+/// PIN used for this run when `--pairing-pin` isn't set
+static GENERATED_PIN: LazyLock<String> =
+    LazyLock::new(|| format!("{:06}", rand::rng().random_range(0..1_000_000)));
+
+/// The PIN clients have to present, or None when pairing isn't required
+pub fn pairing_pin(args: &Args) -> Option<String> {
+    if !args.require_pairing {
+        return None;
+    }
+
+    Some(
+        args.pairing_pin
+            .clone()
+            .filter(|pin| !pin.is_empty())
+            .unwrap_or_else(|| GENERATED_PIN.clone()),
+    )
+}
+
+/// How long a peer gets to present the PIN or its token
+const PAIRING_TIMEOUT: Duration = Duration::from_secs(10);
+/// Wrong PINs in a row from one address before its PINs are refused for
+/// `PIN_LOCKOUT`
+const MAX_PIN_FAILURES: u32 = 5;
+const PIN_LOCKOUT: Duration = Duration::from_secs(60);
+
+/// Wrong PINs from one address
+struct Failures {
+    count: u32,
+    last: Instant,
+    locked_until: Option<Instant>,
+}
+
+static FAILURES: LazyLock<Mutex<HashMap<String, Failures>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Lets a peer through when pairing is off, when it presents a token it was
+/// handed at pairing or when it presents the PIN, in which case it's handed a
+/// new token to present from then on
+pub async fn authenticate<C>(connection: &mut C, args: &Args) -> anyhow::Result<()>
+where
+    C: TransportConnection,
+{
+    let Some(expected) = pairing_pin(args) else {
+        return Ok(());
+    };
+    let name = connection.peer_name();
+    let presented = tokio::time::timeout(PAIRING_TIMEOUT, connection.recv_pin())
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "Rejected {name}: no pairing PIN within {}s",
+                PAIRING_TIMEOUT.as_secs()
+            )
+        })??;
+    // A client that doesn't know about pairing isn't guessing, so this
+    // doesn't count as a wrong PIN
+    let Some(presented) = presented.filter(|presented| !presented.is_empty()) else {
+        return Err(anyhow!("Rejected {name}: no pairing PIN"));
+    };
+
+    if is_pairing_token(&presented) {
+        return Ok(());
+    }
+    let address = connection
+        .peer_address()
+        .unwrap_or_else(|| connection.peer_id());
+    check_pin(&name, &address, &presented, &expected)?;
+
+    let token = new_token();
+    match connection.send_token(&token).await {
+        Ok(true) => {
+            if let Err(err) = SettingsManager::save_pairing_token(&token) {
+                warn!("Failed to remember paired peer {name}: {err}");
+            }
+            info!("Paired {name}");
+        }
+        Ok(false) => info!("Paired {name} for this connection only"),
+        Err(err) => warn!("Paired {name} for this connection only: {err}"),
+    }
+    Ok(())
+}
+
+fn is_pairing_token(presented: &str) -> bool {
+    !presented.is_empty()
+        && SettingsManager::load_pairing_tokens()
+            .iter()
+            .fold(false, |found, token| {
+                found | constant_time_eq(token.as_bytes(), presented.as_bytes())
+            })
+}
+
+/// Compares `presented` to the PIN, refusing every PIN from `address` for a
+/// while after too many wrong ones in a row
+fn check_pin(name: &str, address: &str, presented: &str, expected: &str) -> anyhow::Result<()> {
+    let mut failures = FAILURES
+        .lock()
+        .map_err(|_| anyhow!("Failed to lock pairing failures"))?;
+    let now = Instant::now();
+    // Addresses that stopped guessing are forgotten
+    failures.retain(|_, failures| {
+        failures.locked_until.is_some_and(|until| now < until)
+            || now.duration_since(failures.last) < PIN_LOCKOUT
+    });
+    if failures
+        .get(address)
+        .and_then(|failures| failures.locked_until)
+        .is_some_and(|until| now < until)
+    {
+        return Err(anyhow!(
+            "Rejected {name}: pairing is locked after {MAX_PIN_FAILURES} wrong PINs"
+        ));
+    }
+
+    if constant_time_eq(presented.as_bytes(), expected.as_bytes()) {
+        failures.remove(address);
+        return Ok(());
+    }
+
+    let entry = failures.entry(address.to_string()).or_insert(Failures {
+        count: 0,
+        last: now,
+        locked_until: None,
+    });
+    entry.count += 1;
+    entry.last = now;
+    if entry.count >= MAX_PIN_FAILURES {
+        entry.count = 0;
+        entry.locked_until = Some(now + PIN_LOCKOUT);
+        warn!(
+            "{MAX_PIN_FAILURES} wrong pairing PINs in a row from {address}, refusing its PINs for {}s",
+            PIN_LOCKOUT.as_secs()
+        );
+    }
+    Err(anyhow!("Rejected {name}: wrong pairing PIN"))
+}
+
+/// Compares without stopping at the first difference, so the time taken
+/// doesn't tell how much of a guess was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let diff = a
+        .iter()
+        .zip(b)
+        .fold(0, |diff, (a, b)| diff | std::hint::black_box(a ^ b));
+    a.len() == b.len() && diff == 0
+}
+
+fn new_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::Args;
+use crate::input::InvalidMessage;
+
+/// At most one warning about invalid messages per connection this often
+const WARNING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What happens to a connection sending messages that can't be understood
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+pub enum ParseErrorPolicy {
+    Skip,
+    /// Skips them until there were more than `--max-parse-errors`
+    #[default]
+    SkipWithLimit,
+    Disconnect,
+}
+
+/// Invalid messages seen on one connection
+pub struct ParseErrors {
+    policy: ParseErrorPolicy,
+    limit: u64,
+    count: u64,
+    last_warning: Option<Instant>,
+    /// Invalid messages since the last warning that weren't logged
+    suppressed: u64,
+}
+
+impl ParseErrors {
+    pub fn new(args: &Args) -> Self {
+        Self {
+            policy: args.parse_error_policy,
+            limit: args.max_parse_errors,
+            count: 0,
+            last_warning: None,
+            suppressed: 0,
+        }
+    }
+
+    /// Lets the connection carry on after a message that couldn't be
+    /// understood, unless `--parse-error-policy` says otherwise. Any other
+    /// error is passed on as is
+    pub fn tolerate(&mut self, err: anyhow::Error, peer_name: &str) -> anyhow::Result<()> {
+        let invalid = err.downcast::<InvalidMessage>()?;
+        self.count += 1;
+        match self.policy {
+            ParseErrorPolicy::Disconnect => {
+                return Err(anyhow!("Disconnected {peer_name}: {invalid}"));
+            }
+            ParseErrorPolicy::SkipWithLimit if self.count > self.limit => {
+                return Err(anyhow!(
+                    "Disconnected {peer_name} after {} invalid messages, the last one: {invalid}",
+                    self.count
+                ));
+            }
+            _ => {}
+        }
+
+        if self
+            .last_warning
+            .is_some_and(|last| last.elapsed() < WARNING_INTERVAL)
+        {
+            self.suppressed += 1;
+            return Ok(());
+        }
+        match self.suppressed {
+            0 => warn!("Skipped an invalid message from {peer_name}: {invalid}"),
+            suppressed => warn!(
+                "Skipped an invalid message from {peer_name}, and {suppressed} more since the last warning: {invalid}"
+            ),
+        }
+        self.last_warning = Some(Instant::now());
+        self.suppressed = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_errors(policy: ParseErrorPolicy, limit: u64) -> ParseErrors {
+        ParseErrors::new(&Args {
+            parse_error_policy: policy,
+            max_parse_errors: limit,
+            ..Args::default()
+        })
+    }
+
+    fn invalid() -> anyhow::Error {
+        InvalidMessage(anyhow!("Unknown control")).into()
+    }
+
+    #[test]
+    fn skip_tolerates_any_number() {
+        let mut errors = parse_errors(ParseErrorPolicy::Skip, 0);
+        for _ in 0..100 {
+            assert!(errors.tolerate(invalid(), "phone").is_ok());
+        }
+    }
+
+    #[test]
+    fn skip_with_limit_disconnects_past_the_limit() {
+        let mut errors = parse_errors(ParseErrorPolicy::SkipWithLimit, 3);
+        for _ in 0..3 {
+            assert!(errors.tolerate(invalid(), "phone").is_ok());
+        }
+        assert!(errors.tolerate(invalid(), "phone").is_err());
+    }
+
+    #[test]
+    fn disconnect_stops_at_the_first() {
+        let mut errors = parse_errors(ParseErrorPolicy::Disconnect, 100);
+        assert!(errors.tolerate(invalid(), "phone").is_err());
+    }
+
+    #[test]
+    fn passes_on_other_errors() {
+        let mut errors = parse_errors(ParseErrorPolicy::Skip, 0);
+        let err = errors
+            .tolerate(anyhow!("Connection reset"), "phone")
+            .unwrap_err();
+        assert!(!err.is::<InvalidMessage>());
+        assert_eq!(err.to_string(), "Connection reset");
+        // Only invalid messages count against the limit
+        assert_eq!(errors.count, 0);
+    }
+
+    #[test]
+    fn warns_at_most_once_per_interval() {
+        let mut errors = parse_errors(ParseErrorPolicy::Skip, 0);
+        errors.tolerate(invalid(), "phone").unwrap();
+        errors.tolerate(invalid(), "phone").unwrap();
+        errors.tolerate(invalid(), "phone").unwrap();
+        assert_eq!(errors.suppressed, 2);
+
+        errors.last_warning = Some(Instant::now() - WARNING_INTERVAL);
+        errors.tolerate(invalid(), "phone").unwrap();
+        assert_eq!(errors.suppressed, 0);
+    }
+}
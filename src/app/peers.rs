@@ -0,0 +1,192 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use anyhow::anyhow;
+use log::info;
+use tokio::sync::{Notify, watch};
+
+use super::{Args, TransportKind};
+
+/// A connected peer as shown in the window
+#[cfg(feature = "ui")]
+#[derive(Clone, Debug)]
+pub struct PeerInfo {
+    pub id: u64,
+    pub name: String,
+    pub kind: TransportKind,
+    /// What an access list entry has to match to block this peer
+    pub address: Option<String>,
+    pub messages: u64,
+    /// Messages held back by `--max-message-rate`
+    pub throttled: u64,
+    /// Messages skipped because they couldn't be understood
+    pub invalid: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    messages: AtomicU64,
+    throttled: AtomicU64,
+    invalid: AtomicU64,
+}
+
+#[cfg_attr(not(feature = "ui"), allow(dead_code))]
+struct Entry {
+    name: String,
+    kind: TransportKind,
+    address: Option<String>,
+    counters: Arc<Counters>,
+    disconnect: Arc<Notify>,
+}
+
+static CONNECTED: LazyLock<Mutex<BTreeMap<u64, Entry>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+/// Bumped whenever a peer connects or leaves
+static CHANGED: LazyLock<watch::Sender<()>> = LazyLock::new(|| watch::channel(()).0);
+
+/// Keeps a peer listed, and counted against the connection limits, for as
+/// long as its connection is served
+pub struct Registration {
+    id: u64,
+    counters: Arc<Counters>,
+    disconnect: Arc<Notify>,
+}
+
+impl Registration {
+    /// Resolves once the peer is asked to go away
+    pub async fn disconnected(&self) {
+        self.disconnect.notified().await;
+    }
+
+    pub fn count_message(&self) {
+        self.counters.messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a throttled message, returning how many there were before
+    pub fn count_throttled(&self) -> u64 {
+        self.counters.throttled.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn count_invalid(&self) {
+        self.counters.invalid.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn messages(&self) -> u64 {
+        self.counters.messages.load(Ordering::Relaxed)
+    }
+
+    pub fn throttled(&self) -> u64 {
+        self.counters.throttled.load(Ordering::Relaxed)
+    }
+
+    pub fn invalid(&self) -> u64 {
+        self.counters.invalid.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Ok(mut connected) = CONNECTED.lock() {
+            connected.remove(&self.id);
+        }
+        CHANGED.send_replace(());
+    }
+}
+
+/// Lists a new peer, unless that would go over `--max-connections` or
+/// `--max-connections-per-transport`
+pub fn register(
+    kind: TransportKind,
+    name: &str,
+    address: Option<String>,
+    args: &Args,
+) -> anyhow::Result<Registration> {
+    let mut connected = CONNECTED
+        .lock()
+        .map_err(|_| anyhow!("Failed to lock connected peers"))?;
+    let total = connected.len();
+    let on_transport = connected
+        .values()
+        .filter(|entry| entry.kind == kind)
+        .count();
+    if args.max_connections > 0 && total >= args.max_connections {
+        return Err(anyhow!(
+            "Rejected {name}: {total} connections are open, the limit is {}",
+            args.max_connections
+        ));
+    }
+    if args.max_connections_per_transport > 0 && on_transport >= args.max_connections_per_transport
+    {
+        return Err(anyhow!(
+            "Rejected {name}: {on_transport} {kind:?} connections are open, the limit is {}",
+            args.max_connections_per_transport
+        ));
+    }
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let counters = Arc::new(Counters::default());
+    let disconnect = Arc::new(Notify::new());
+    connected.insert(
+        id,
+        Entry {
+            name: name.to_string(),
+            kind,
+            address,
+            counters: counters.clone(),
+            disconnect: disconnect.clone(),
+        },
+    );
+    drop(connected);
+    CHANGED.send_replace(());
+    info!(
+        "{} connections open, {} on {kind:?}",
+        total + 1,
+        on_transport + 1
+    );
+
+    Ok(Registration {
+        id,
+        counters,
+        disconnect,
+    })
+}
+
+#[cfg(feature = "ui")]
+pub fn connected_peers() -> Vec<PeerInfo> {
+    let Ok(connected) = CONNECTED.lock() else {
+        return Vec::new();
+    };
+    connected
+        .iter()
+        .map(|(id, entry)| PeerInfo {
+            id: *id,
+            name: entry.name.clone(),
+            kind: entry.kind,
+            address: entry.address.clone(),
+            messages: entry.counters.messages.load(Ordering::Relaxed),
+            throttled: entry.counters.throttled.load(Ordering::Relaxed),
+            invalid: entry.counters.invalid.load(Ordering::Relaxed),
+        })
+        .collect()
+}
+
+#[cfg(feature = "ui")]
+pub fn disconnect_peer(id: u64) {
+    if let Ok(connected) = CONNECTED.lock()
+        && let Some(entry) = connected.get(&id)
+    {
+        entry.disconnect.notify_one();
+    }
+}
+
+/// Changes whenever the list of connected peers does
+#[cfg(feature = "ui")]
+pub fn watch_peers() -> watch::Receiver<()> {
+    CHANGED.subscribe()
+}
@@ -0,0 +1,49 @@
+use qrcode::{QrCode, render::unicode::Dense1x2};
+
+use super::{Args, TransportKind};
+use crate::transport::connection_urls;
+
+/// What phones scan to connect: the URLs of the first transport in `kinds`
+/// that listens on the network, one per line
+pub fn connection_info(
+    args: &Args,
+    kinds: impl IntoIterator<Item = TransportKind>,
+) -> Option<String> {
+    kinds
+        .into_iter()
+        .map(|kind| connection_urls(kind, args))
+        .find(|urls| !urls.is_empty())
+        .map(|urls| urls.join("\n"))
+}
+
+/// `data` as a QR code drawn with Unicode half blocks, two modules per
+/// character. Colors are swapped so it scans on dark terminals
+pub fn render_text(data: &str) -> anyhow::Result<String> {
+    Ok(QrCode::new(data)?
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
+
+/// `data` as a square of modules, row by row, true for dark ones
+#[cfg(feature = "ui")]
+pub struct QrModules {
+    pub width: usize,
+    pub dark: Vec<bool>,
+}
+
+#[cfg(feature = "ui")]
+impl QrModules {
+    pub fn new(data: &str) -> anyhow::Result<Self> {
+        let code = QrCode::new(data)?;
+        Ok(Self {
+            width: code.width(),
+            dark: code
+                .to_colors()
+                .into_iter()
+                .map(|color| color == qrcode::Color::Dark)
+                .collect(),
+        })
+    }
+}
@@ -1,5 +1,15 @@
 #[cfg(feature = "bluetooth")]
 use crate::transport::bluetooth::{BluetoothTransport, BluetoothTransportConnection};
+#[cfg(feature = "mqtt")]
+use crate::transport::mqtt::{MqttTransport, MqttTransportConnection};
+#[cfg(feature = "serial")]
+use crate::transport::serial::{SerialTransport, SerialTransportConnection};
+#[cfg(feature = "tcp")]
+use crate::transport::tcp::{TcpTransport, TcpTransportConnection};
+#[cfg(feature = "udp")]
+use crate::transport::udp::{UdpTransport, UdpTransportConnection};
+#[cfg(all(unix, feature = "unix"))]
+use crate::transport::unix::{UnixTransport, UnixTransportConnection};
 #[cfg(feature = "ws")]
 use crate::transport::ws::{WsTransport, WsTransportConnection};
 use crate::{
@@ -8,11 +18,22 @@ use crate::{
     transport::{Transport, TransportConnection},
 };
 
+#[allow(clippy::large_enum_variant)]
 pub enum RuntimeTransport {
     #[cfg(feature = "ws")]
     Ws(WsTransport),
     #[cfg(feature = "bluetooth")]
     Bluetooth(BluetoothTransport),
+    #[cfg(feature = "tcp")]
+    Tcp(TcpTransport),
+    #[cfg(feature = "udp")]
+    Udp(UdpTransport),
+    #[cfg(feature = "mqtt")]
+    Mqtt(MqttTransport),
+    #[cfg(all(unix, feature = "unix"))]
+    Unix(UnixTransport),
+    #[cfg(feature = "serial")]
+    Serial(SerialTransport),
 }
 
 impl RuntimeTransport {
@@ -22,6 +43,16 @@ impl RuntimeTransport {
             TransportKind::Ws => Self::Ws(WsTransport::new()),
             #[cfg(feature = "bluetooth")]
             TransportKind::Bluetooth => Self::Bluetooth(BluetoothTransport::new()),
+            #[cfg(feature = "tcp")]
+            TransportKind::Tcp => Self::Tcp(TcpTransport::new()),
+            #[cfg(feature = "udp")]
+            TransportKind::Udp => Self::Udp(UdpTransport::new()),
+            #[cfg(feature = "mqtt")]
+            TransportKind::Mqtt => Self::Mqtt(MqttTransport::new()),
+            #[cfg(all(unix, feature = "unix"))]
+            TransportKind::Unix => Self::Unix(UnixTransport::new()),
+            #[cfg(feature = "serial")]
+            TransportKind::Serial => Self::Serial(SerialTransport::new()),
         }
     }
 
@@ -31,6 +62,27 @@ impl RuntimeTransport {
             Self::Ws(transport) => transport.listen(args).await,
             #[cfg(feature = "bluetooth")]
             Self::Bluetooth(transport) => transport.listen(args).await,
+            #[cfg(feature = "tcp")]
+            Self::Tcp(transport) => transport.listen(args).await,
+            #[cfg(feature = "udp")]
+            Self::Udp(transport) => transport.listen(args).await,
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(transport) => transport.listen(args).await,
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(transport) => transport.listen(args).await,
+            #[cfg(feature = "serial")]
+            Self::Serial(transport) => transport.listen(args).await,
+        }
+    }
+
+    /// SHA-256 fingerprint of the certificate, when the transport uses TLS
+    #[cfg(feature = "ui")]
+    pub fn tls_fingerprint(&self) -> Option<String> {
+        match self {
+            #[cfg(feature = "ws")]
+            Self::Ws(transport) => transport.tls_fingerprint(),
+            #[allow(unreachable_patterns)]
+            _ => None,
         }
     }
 
@@ -42,6 +94,16 @@ impl RuntimeTransport {
             Self::Bluetooth(transport) => {
                 Ok(RuntimeConnection::Bluetooth(transport.accept(args).await?))
             }
+            #[cfg(feature = "tcp")]
+            Self::Tcp(transport) => Ok(RuntimeConnection::Tcp(transport.accept(args).await?)),
+            #[cfg(feature = "udp")]
+            Self::Udp(transport) => Ok(RuntimeConnection::Udp(transport.accept(args).await?)),
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(transport) => Ok(RuntimeConnection::Mqtt(transport.accept(args).await?)),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(transport) => Ok(RuntimeConnection::Unix(transport.accept(args).await?)),
+            #[cfg(feature = "serial")]
+            Self::Serial(transport) => Ok(RuntimeConnection::Serial(transport.accept(args).await?)),
         }
     }
 }
@@ -52,6 +114,16 @@ pub enum RuntimeConnection {
     Ws(WsTransportConnection),
     #[cfg(feature = "bluetooth")]
     Bluetooth(BluetoothTransportConnection),
+    #[cfg(feature = "tcp")]
+    Tcp(TcpTransportConnection),
+    #[cfg(feature = "udp")]
+    Udp(UdpTransportConnection),
+    #[cfg(feature = "mqtt")]
+    Mqtt(MqttTransportConnection),
+    #[cfg(all(unix, feature = "unix"))]
+    Unix(UnixTransportConnection),
+    #[cfg(feature = "serial")]
+    Serial(SerialTransportConnection),
 }
 
 impl TransportConnection for RuntimeConnection {
@@ -61,6 +133,130 @@ impl TransportConnection for RuntimeConnection {
             Self::Ws(connection) => connection.peer_name(),
             #[cfg(feature = "bluetooth")]
             Self::Bluetooth(connection) => connection.peer_name(),
+            #[cfg(feature = "tcp")]
+            Self::Tcp(connection) => connection.peer_name(),
+            #[cfg(feature = "udp")]
+            Self::Udp(connection) => connection.peer_name(),
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(connection) => connection.peer_name(),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(connection) => connection.peer_name(),
+            #[cfg(feature = "serial")]
+            Self::Serial(connection) => connection.peer_name(),
+        }
+    }
+
+    fn peer_id(&self) -> String {
+        match self {
+            #[cfg(feature = "ws")]
+            Self::Ws(connection) => connection.peer_id(),
+            #[cfg(feature = "bluetooth")]
+            Self::Bluetooth(connection) => connection.peer_id(),
+            #[cfg(feature = "tcp")]
+            Self::Tcp(connection) => connection.peer_id(),
+            #[cfg(feature = "udp")]
+            Self::Udp(connection) => connection.peer_id(),
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(connection) => connection.peer_id(),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(connection) => connection.peer_id(),
+            #[cfg(feature = "serial")]
+            Self::Serial(connection) => connection.peer_id(),
+        }
+    }
+
+    fn slot_hint(&self) -> String {
+        match self {
+            #[cfg(feature = "ws")]
+            Self::Ws(connection) => connection.slot_hint(),
+            #[cfg(feature = "bluetooth")]
+            Self::Bluetooth(connection) => connection.slot_hint(),
+            #[cfg(feature = "tcp")]
+            Self::Tcp(connection) => connection.slot_hint(),
+            #[cfg(feature = "udp")]
+            Self::Udp(connection) => connection.slot_hint(),
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(connection) => connection.slot_hint(),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(connection) => connection.slot_hint(),
+            #[cfg(feature = "serial")]
+            Self::Serial(connection) => connection.slot_hint(),
+        }
+    }
+
+    fn peer_address(&self) -> Option<String> {
+        match self {
+            #[cfg(feature = "ws")]
+            Self::Ws(connection) => connection.peer_address(),
+            #[cfg(feature = "bluetooth")]
+            Self::Bluetooth(connection) => connection.peer_address(),
+            #[cfg(feature = "tcp")]
+            Self::Tcp(connection) => connection.peer_address(),
+            #[cfg(feature = "udp")]
+            Self::Udp(connection) => connection.peer_address(),
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(connection) => connection.peer_address(),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(connection) => connection.peer_address(),
+            #[cfg(feature = "serial")]
+            Self::Serial(connection) => connection.peer_address(),
+        }
+    }
+
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>> {
+        match self {
+            #[cfg(feature = "ws")]
+            Self::Ws(connection) => connection.recv_pin().await,
+            #[cfg(feature = "bluetooth")]
+            Self::Bluetooth(connection) => connection.recv_pin().await,
+            #[cfg(feature = "tcp")]
+            Self::Tcp(connection) => connection.recv_pin().await,
+            #[cfg(feature = "udp")]
+            Self::Udp(connection) => connection.recv_pin().await,
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(connection) => connection.recv_pin().await,
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(connection) => connection.recv_pin().await,
+            #[cfg(feature = "serial")]
+            Self::Serial(connection) => connection.recv_pin().await,
+        }
+    }
+
+    fn reject(&mut self) {
+        match self {
+            #[cfg(feature = "ws")]
+            Self::Ws(connection) => connection.reject(),
+            #[cfg(feature = "bluetooth")]
+            Self::Bluetooth(connection) => connection.reject(),
+            #[cfg(feature = "tcp")]
+            Self::Tcp(connection) => connection.reject(),
+            #[cfg(feature = "udp")]
+            Self::Udp(connection) => connection.reject(),
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(connection) => connection.reject(),
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(connection) => connection.reject(),
+            #[cfg(feature = "serial")]
+            Self::Serial(connection) => connection.reject(),
+        }
+    }
+
+    async fn send_token(&mut self, token: &str) -> anyhow::Result<bool> {
+        match self {
+            #[cfg(feature = "ws")]
+            Self::Ws(connection) => connection.send_token(token).await,
+            #[cfg(feature = "bluetooth")]
+            Self::Bluetooth(connection) => connection.send_token(token).await,
+            #[cfg(feature = "tcp")]
+            Self::Tcp(connection) => connection.send_token(token).await,
+            #[cfg(feature = "udp")]
+            Self::Udp(connection) => connection.send_token(token).await,
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(connection) => connection.send_token(token).await,
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(connection) => connection.send_token(token).await,
+            #[cfg(feature = "serial")]
+            Self::Serial(connection) => connection.send_token(token).await,
         }
     }
 
@@ -70,6 +266,16 @@ impl TransportConnection for RuntimeConnection {
             Self::Ws(connection) => connection.recv_message().await,
             #[cfg(feature = "bluetooth")]
             Self::Bluetooth(connection) => connection.recv_message().await,
+            #[cfg(feature = "tcp")]
+            Self::Tcp(connection) => connection.recv_message().await,
+            #[cfg(feature = "udp")]
+            Self::Udp(connection) => connection.recv_message().await,
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(connection) => connection.recv_message().await,
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix(connection) => connection.recv_message().await,
+            #[cfg(feature = "serial")]
+            Self::Serial(connection) => connection.recv_message().await,
         }
     }
 }
@@ -0,0 +1,81 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use log::{info, warn};
+
+use super::slots::PlayerSlot;
+use crate::controller::Controller;
+
+/// A virtual device together with everything that belongs to its peer, so it
+/// can outlive a single connection
+pub struct Session {
+    pub controller: Controller,
+    pub player_slot: Option<PlayerSlot>,
+}
+
+struct Parked {
+    session: Session,
+    generation: u64,
+}
+
+#[derive(Default)]
+struct Registry {
+    parked: HashMap<String, Parked>,
+    next_generation: u64,
+}
+
+/// Sessions waiting for their peer to reconnect, keyed by peer identity
+static PARKED_SESSIONS: LazyLock<Mutex<Registry>> =
+    LazyLock::new(|| Mutex::new(Registry::default()));
+
+/// Takes back the session a peer left within the grace period
+pub fn resume(peer_id: &str) -> Option<Session> {
+    let mut registry = PARKED_SESSIONS.lock().ok()?;
+    registry.parked.remove(peer_id).map(|parked| parked.session)
+}
+
+/// Keeps the session's device alive with all inputs neutralized, and destroys
+/// it if the peer doesn't reconnect within `grace`
+pub fn park(peer_id: String, mut session: Session, grace: Duration) {
+    if let Err(err) = session.controller.neutralize() {
+        warn!("Failed to neutralize inputs for {peer_id}: {err}");
+    }
+
+    let Ok(mut registry) = PARKED_SESSIONS.lock() else {
+        return;
+    };
+    registry.next_generation += 1;
+    let generation = registry.next_generation;
+    registry.parked.insert(
+        peer_id.clone(),
+        Parked {
+            session,
+            generation,
+        },
+    );
+    drop(registry);
+
+    info!(
+        "Keeping controller for {peer_id} for {}ms",
+        grace.as_millis()
+    );
+    tokio::spawn(async move {
+        tokio::time::sleep(grace).await;
+
+        let Ok(mut registry) = PARKED_SESSIONS.lock() else {
+            return;
+        };
+        // The peer may have resumed and left again in the meantime
+        if registry
+            .parked
+            .get(&peer_id)
+            .is_some_and(|parked| parked.generation == generation)
+        {
+            registry.parked.remove(&peer_id);
+            info!("Grace period expired, removed controller for {peer_id}");
+        }
+    });
+}
@@ -0,0 +1,155 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+#[cfg(feature = "ui")]
+use gpui_component::ThemeMode;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::{Args, approval::PeerPolicy};
+
+/// Serializes read-modify-write cycles on the config file, since connection
+/// tasks and the UI may update it concurrently
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct SettingsManager;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct StoredSettings {
+    #[serde(flatten)]
+    args: Args,
+    #[cfg(feature = "ui")]
+    #[serde(default)]
+    theme_mode: Option<ThemeMode>,
+    /// Peer identity to player slot
+    #[serde(default)]
+    player_slots: BTreeMap<String, u8>,
+    /// Tokens handed to peers that presented the pairing PIN, which they
+    /// present instead from then on
+    #[serde(default)]
+    pairing_tokens: BTreeSet<String>,
+    /// Peer identity to the approval answer remembered for it
+    #[serde(default)]
+    peer_policies: BTreeMap<String, PeerPolicy>,
+}
+
+impl SettingsManager {
+    /// Directory holding the config file and anything else that has to
+    /// persist between runs
+    pub fn config_dir() -> PathBuf {
+        let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+        path.push("droidpad-gamepad");
+        path
+    }
+
+    fn config_path() -> PathBuf {
+        Self::config_dir().join("config.json")
+    }
+
+    fn load_stored() -> StoredSettings {
+        let path = Self::config_path();
+        if path.exists() {
+            match fs::read_to_string(&path) {
+                Ok(content) => match serde_json::from_str::<StoredSettings>(&content) {
+                    Ok(settings) => return settings,
+                    Err(err) => warn!("Failed to parse config file at {path:?}: {err}"),
+                },
+                Err(err) => warn!("Failed to read config file at {path:?}: {err}"),
+            }
+        }
+
+        StoredSettings {
+            args: Args::default(),
+            #[cfg(feature = "ui")]
+            theme_mode: None,
+            player_slots: BTreeMap::new(),
+            pairing_tokens: BTreeSet::new(),
+            peer_policies: BTreeMap::new(),
+        }
+    }
+
+    fn save_stored(settings: &StoredSettings) -> anyhow::Result<()> {
+        let path = Self::config_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(settings)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn update_stored(update: impl FnOnce(&mut StoredSettings)) -> anyhow::Result<()> {
+        let _guard = CONFIG_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let mut settings = Self::load_stored();
+        update(&mut settings);
+        Self::save_stored(&settings)
+    }
+
+    #[cfg(feature = "ui")]
+    pub fn save(args: &Args) -> anyhow::Result<()> {
+        Self::update_stored(|settings| settings.args = args.clone())
+    }
+
+    #[cfg(feature = "ui")]
+    pub fn load_ui_settings(default_theme_mode: ThemeMode) -> (Args, Option<ThemeMode>) {
+        let settings = Self::load_stored();
+        let theme_mode = settings.theme_mode.or(Some(default_theme_mode));
+        (
+            settings.args,
+            theme_mode.filter(|_| settings.theme_mode.is_some()),
+        )
+    }
+
+    #[cfg(feature = "ui")]
+    pub fn save_theme_mode(theme_mode: ThemeMode) -> anyhow::Result<()> {
+        Self::update_stored(|settings| settings.theme_mode = Some(theme_mode))
+    }
+
+    pub fn load_player_slots() -> BTreeMap<String, u8> {
+        let _guard = CONFIG_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        Self::load_stored().player_slots
+    }
+
+    pub fn save_player_slot(peer_id: &str, slot: u8) -> anyhow::Result<()> {
+        Self::update_stored(|settings| {
+            settings.player_slots.insert(peer_id.to_string(), slot);
+        })
+    }
+
+    pub fn load_pairing_tokens() -> BTreeSet<String> {
+        let _guard = CONFIG_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        Self::load_stored().pairing_tokens
+    }
+
+    pub fn save_pairing_token(token: &str) -> anyhow::Result<()> {
+        Self::update_stored(|settings| {
+            settings.pairing_tokens.insert(token.to_string());
+        })
+    }
+
+    #[cfg(feature = "ui")]
+    pub fn forget_pairing_tokens() -> anyhow::Result<()> {
+        Self::update_stored(|settings| settings.pairing_tokens.clear())
+    }
+
+    pub fn peer_policy(peer_id: &str) -> Option<PeerPolicy> {
+        let _guard = CONFIG_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        Self::load_stored().peer_policies.get(peer_id).copied()
+    }
+
+    #[cfg(feature = "ui")]
+    pub fn save_peer_policy(peer_id: &str, policy: PeerPolicy) -> anyhow::Result<()> {
+        Self::update_stored(|settings| {
+            settings.peer_policies.insert(peer_id.to_string(), policy);
+        })
+    }
+
+    #[cfg(feature = "ui")]
+    pub fn forget_peer_policies() -> anyhow::Result<()> {
+        Self::update_stored(|settings| settings.peer_policies.clear())
+    }
+}
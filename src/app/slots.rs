@@ -0,0 +1,69 @@
+use std::{
+    collections::BTreeMap,
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::anyhow;
+use log::{info, warn};
+
+use super::SettingsManager;
+
+/// Slots currently held by a connection, slot to peer identity
+static ACTIVE_SLOTS: LazyLock<Mutex<BTreeMap<u8, String>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// A player slot held for as long as the guard lives
+pub struct PlayerSlot {
+    slot: u8,
+}
+
+impl PlayerSlot {
+    /// Gives a known peer the slot it had before when that slot is free,
+    /// otherwise the lowest free slot not remembered for another peer
+    pub fn acquire(peer_id: &str, max_players: u8) -> anyhow::Result<Self> {
+        let mut active = ACTIVE_SLOTS
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock player slots"))?;
+        let remembered = SettingsManager::load_player_slots();
+        let is_free = |slot: &u8| !active.contains_key(slot);
+
+        let known_slot = remembered
+            .get(peer_id)
+            .copied()
+            .filter(|slot| (1..=max_players).contains(slot) && is_free(slot));
+        let slot = known_slot
+            .or_else(|| {
+                (1..=max_players).find(|slot| {
+                    is_free(slot) && !remembered.values().any(|reserved| reserved == slot)
+                })
+            })
+            .or_else(|| (1..=max_players).find(is_free))
+            .ok_or_else(|| anyhow!("All {max_players} player slots are in use"))?;
+
+        active.insert(slot, peer_id.to_string());
+        drop(active);
+
+        // A known peer whose slot is taken gets a temporary one, its remembered
+        // slot stays the same
+        if !remembered.contains_key(peer_id)
+            && let Err(err) = SettingsManager::save_player_slot(peer_id, slot)
+        {
+            warn!("Failed to remember player slot {slot} for {peer_id}: {err}");
+        }
+        info!("Assigned player slot {slot} to {peer_id}");
+
+        Ok(Self { slot })
+    }
+
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+}
+
+impl Drop for PlayerSlot {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE_SLOTS.lock() {
+            active.remove(&self.slot);
+        }
+    }
+}
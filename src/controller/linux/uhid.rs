@@ -0,0 +1,652 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    os::fd::AsRawFd,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use anyhow::{Context, anyhow};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controller::ControllerBackend,
+    input::{Key, KeyEvent},
+};
+
+const UHID_PATH: &str = "/dev/uhid";
+
+const UHID_DESTROY: u32 = 1;
+const UHID_START: u32 = 2;
+const UHID_STOP: u32 = 3;
+const UHID_OPEN: u32 = 4;
+const UHID_CLOSE: u32 = 5;
+const UHID_OUTPUT: u32 = 6;
+const UHID_GET_REPORT: u32 = 9;
+const UHID_GET_REPORT_REPLY: u32 = 10;
+const UHID_CREATE2: u32 = 11;
+const UHID_INPUT2: u32 = 12;
+const UHID_SET_REPORT: u32 = 13;
+const UHID_SET_REPORT_REPLY: u32 = 14;
+
+const UHID_INPUT_REPORT: u8 = 2;
+
+const UHID_NAME_LEN: usize = 128;
+const UHID_PHYS_LEN: usize = 64;
+const UHID_UNIQ_LEN: usize = 64;
+const UHID_DATA_MAX: usize = 4096;
+// `struct uhid_event` is packed: a u32 type followed by a union whose largest
+// member is `uhid_create2_req`.
+const UHID_EVENT_SIZE: usize =
+    4 + UHID_NAME_LEN + UHID_PHYS_LEN + UHID_UNIQ_LEN + 2 + 2 + 4 * 4 + UHID_DATA_MAX;
+
+/// How often the event reader checks whether its controller is gone
+const EVENT_POLL_MS: i32 = 200;
+
+const BUS_BLUETOOTH: u16 = 0x05;
+const BUS_VIRTUAL: u16 = 0x06;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+pub enum UhidDescriptor {
+    /// Plain HID gamepad handled by `hid-generic`
+    #[default]
+    Generic,
+    /// Xbox Wireless Controller (Bluetooth) layout handled by `hid-microsoft`
+    Xbox,
+}
+
+/// No report ID. Buttons (16 bits), hat (4 bits + padding), X/Y/Rx/Ry (signed
+/// 16 bits), Z/Rz triggers (8 bits).
+#[rustfmt::skip]
+const GENERIC_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,       // Usage Page (Generic Desktop)
+    0x09, 0x05,       // Usage (Game Pad)
+    0xA1, 0x01,       // Collection (Application)
+    0x05, 0x09,       //   Usage Page (Button)
+    0x19, 0x01,       //   Usage Minimum (1)
+    0x29, 0x10,       //   Usage Maximum (16)
+    0x15, 0x00,       //   Logical Minimum (0)
+    0x25, 0x01,       //   Logical Maximum (1)
+    0x75, 0x01,       //   Report Size (1)
+    0x95, 0x10,       //   Report Count (16)
+    0x81, 0x02,       //   Input (Data, Variable, Absolute)
+    0x05, 0x01,       //   Usage Page (Generic Desktop)
+    0x09, 0x39,       //   Usage (Hat Switch)
+    0x15, 0x00,       //   Logical Minimum (0)
+    0x25, 0x07,       //   Logical Maximum (7)
+    0x35, 0x00,       //   Physical Minimum (0)
+    0x46, 0x3B, 0x01, //   Physical Maximum (315)
+    0x65, 0x14,       //   Unit (Degrees)
+    0x75, 0x04,       //   Report Size (4)
+    0x95, 0x01,       //   Report Count (1)
+    0x81, 0x42,       //   Input (Data, Variable, Absolute, Null State)
+    0x65, 0x00,       //   Unit (None)
+    0x45, 0x00,       //   Physical Maximum (0)
+    0x81, 0x01,       //   Input (Constant)
+    0x09, 0x30,       //   Usage (X)
+    0x09, 0x31,       //   Usage (Y)
+    0x09, 0x33,       //   Usage (Rx)
+    0x09, 0x34,       //   Usage (Ry)
+    0x16, 0x00, 0x80, //   Logical Minimum (-32768)
+    0x26, 0xFF, 0x7F, //   Logical Maximum (32767)
+    0x75, 0x10,       //   Report Size (16)
+    0x95, 0x04,       //   Report Count (4)
+    0x81, 0x02,       //   Input (Data, Variable, Absolute)
+    0x09, 0x32,       //   Usage (Z)
+    0x09, 0x35,       //   Usage (Rz)
+    0x15, 0x00,       //   Logical Minimum (0)
+    0x26, 0xFF, 0x00, //   Logical Maximum (255)
+    0x75, 0x08,       //   Report Size (8)
+    0x95, 0x02,       //   Report Count (2)
+    0x81, 0x02,       //   Input (Data, Variable, Absolute)
+    0xC0,             // End Collection
+];
+
+/// Report ID 1 of the Xbox Wireless Controller's Bluetooth descriptor. Sticks
+/// (unsigned 16 bits), brake/accelerator (10 bits), hat (1-8), 15 buttons and
+/// the consumer AC Back bit.
+#[rustfmt::skip]
+const XBOX_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,                   // Usage Page (Generic Desktop)
+    0x09, 0x05,                   // Usage (Game Pad)
+    0xA1, 0x01,                   // Collection (Application)
+    0x85, 0x01,                   //   Report ID (1)
+    0x09, 0x01,                   //   Usage (Pointer)
+    0xA1, 0x00,                   //   Collection (Physical)
+    0x09, 0x30,                   //     Usage (X)
+    0x09, 0x31,                   //     Usage (Y)
+    0x15, 0x00,                   //     Logical Minimum (0)
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535)
+    0x95, 0x02,                   //     Report Count (2)
+    0x75, 0x10,                   //     Report Size (16)
+    0x81, 0x02,                   //     Input (Data, Variable, Absolute)
+    0xC0,                         //   End Collection
+    0x09, 0x01,                   //   Usage (Pointer)
+    0xA1, 0x00,                   //   Collection (Physical)
+    0x09, 0x32,                   //     Usage (Z)
+    0x09, 0x35,                   //     Usage (Rz)
+    0x15, 0x00,                   //     Logical Minimum (0)
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //     Logical Maximum (65535)
+    0x95, 0x02,                   //     Report Count (2)
+    0x75, 0x10,                   //     Report Size (16)
+    0x81, 0x02,                   //     Input (Data, Variable, Absolute)
+    0xC0,                         //   End Collection
+    0x05, 0x02,                   //   Usage Page (Simulation Controls)
+    0x09, 0xC5,                   //   Usage (Brake)
+    0x15, 0x00,                   //   Logical Minimum (0)
+    0x26, 0xFF, 0x03,             //   Logical Maximum (1023)
+    0x95, 0x01,                   //   Report Count (1)
+    0x75, 0x0A,                   //   Report Size (10)
+    0x81, 0x02,                   //   Input (Data, Variable, Absolute)
+    0x15, 0x00,                   //   Logical Minimum (0)
+    0x25, 0x00,                   //   Logical Maximum (0)
+    0x75, 0x06,                   //   Report Size (6)
+    0x95, 0x01,                   //   Report Count (1)
+    0x81, 0x03,                   //   Input (Constant, Variable)
+    0x05, 0x02,                   //   Usage Page (Simulation Controls)
+    0x09, 0xC4,                   //   Usage (Accelerator)
+    0x15, 0x00,                   //   Logical Minimum (0)
+    0x26, 0xFF, 0x03,             //   Logical Maximum (1023)
+    0x95, 0x01,                   //   Report Count (1)
+    0x75, 0x0A,                   //   Report Size (10)
+    0x81, 0x02,                   //   Input (Data, Variable, Absolute)
+    0x15, 0x00,                   //   Logical Minimum (0)
+    0x25, 0x00,                   //   Logical Maximum (0)
+    0x75, 0x06,                   //   Report Size (6)
+    0x95, 0x01,                   //   Report Count (1)
+    0x81, 0x03,                   //   Input (Constant, Variable)
+    0x05, 0x01,                   //   Usage Page (Generic Desktop)
+    0x09, 0x39,                   //   Usage (Hat Switch)
+    0x15, 0x01,                   //   Logical Minimum (1)
+    0x25, 0x08,                   //   Logical Maximum (8)
+    0x35, 0x00,                   //   Physical Minimum (0)
+    0x46, 0x3B, 0x01,             //   Physical Maximum (315)
+    0x66, 0x14, 0x00,             //   Unit (Degrees)
+    0x75, 0x04,                   //   Report Size (4)
+    0x95, 0x01,                   //   Report Count (1)
+    0x81, 0x42,                   //   Input (Data, Variable, Absolute, Null State)
+    0x75, 0x04,                   //   Report Size (4)
+    0x95, 0x01,                   //   Report Count (1)
+    0x15, 0x00,                   //   Logical Minimum (0)
+    0x25, 0x00,                   //   Logical Maximum (0)
+    0x35, 0x00,                   //   Physical Minimum (0)
+    0x45, 0x00,                   //   Physical Maximum (0)
+    0x65, 0x00,                   //   Unit (None)
+    0x81, 0x03,                   //   Input (Constant, Variable)
+    0x05, 0x09,                   //   Usage Page (Button)
+    0x19, 0x01,                   //   Usage Minimum (1)
+    0x29, 0x0F,                   //   Usage Maximum (15)
+    0x15, 0x00,                   //   Logical Minimum (0)
+    0x25, 0x01,                   //   Logical Maximum (1)
+    0x75, 0x01,                   //   Report Size (1)
+    0x95, 0x0F,                   //   Report Count (15)
+    0x81, 0x02,                   //   Input (Data, Variable, Absolute)
+    0x15, 0x00,                   //   Logical Minimum (0)
+    0x25, 0x00,                   //   Logical Maximum (0)
+    0x75, 0x01,                   //   Report Size (1)
+    0x95, 0x01,                   //   Report Count (1)
+    0x81, 0x03,                   //   Input (Constant, Variable)
+    0x05, 0x0C,                   //   Usage Page (Consumer)
+    0x0A, 0x24, 0x02,             //   Usage (AC Back)
+    0x15, 0x00,                   //   Logical Minimum (0)
+    0x25, 0x01,                   //   Logical Maximum (1)
+    0x95, 0x01,                   //   Report Count (1)
+    0x75, 0x01,                   //   Report Size (1)
+    0x81, 0x02,                   //   Input (Data, Variable, Absolute)
+    0x15, 0x00,                   //   Logical Minimum (0)
+    0x25, 0x00,                   //   Logical Maximum (0)
+    0x75, 0x07,                   //   Report Size (7)
+    0x95, 0x01,                   //   Report Count (1)
+    0x81, 0x03,                   //   Input (Constant, Variable)
+    0xC0,                         // End Collection
+];
+
+impl UhidDescriptor {
+    fn report_descriptor(self) -> &'static [u8] {
+        match self {
+            Self::Generic => GENERIC_REPORT_DESCRIPTOR,
+            Self::Xbox => XBOX_REPORT_DESCRIPTOR,
+        }
+    }
+
+    fn bus(self) -> u16 {
+        match self {
+            Self::Generic => BUS_VIRTUAL,
+            Self::Xbox => BUS_BLUETOOTH,
+        }
+    }
+
+    fn vendor_product(self) -> (u32, u32) {
+        match self {
+            // pid.codes test VID/PID, so SDL doesn't pick a vendor specific driver
+            Self::Generic => (0x1209, 0x0001),
+            Self::Xbox => (0x045e, 0x02fd),
+        }
+    }
+}
+
+/// Buttons are numbered per layout, see `button_number`
+#[derive(Default)]
+struct Report {
+    buttons: u16,
+    /// View button, which the Xbox layout reports as the consumer AC Back bit
+    back: bool,
+    dpad_up: bool,
+    dpad_down: bool,
+    dpad_left: bool,
+    dpad_right: bool,
+    left_x: f32,
+    left_y: f32,
+    right_x: f32,
+    right_y: f32,
+    left_trigger: bool,
+    right_trigger: bool,
+}
+
+impl Report {
+    fn set_button(&mut self, number: u16, state: KeyEvent) {
+        let mask = 1 << (number - 1);
+        if bool::from(state) {
+            self.buttons |= mask;
+        } else {
+            self.buttons &= !mask;
+        }
+    }
+
+    fn update(&mut self, key: Key, descriptor: UhidDescriptor) {
+        match key {
+            Key::LeftJoystickX(v) => self.left_x = v,
+            Key::LeftJoystickY(v) => self.left_y = -v,
+            Key::RightJoystickX(v) => self.right_x = v,
+            Key::RightJoystickY(v) => self.right_y = -v,
+            Key::DPadUp(state) => self.dpad_up = state.into(),
+            Key::DPadDown(state) => self.dpad_down = state.into(),
+            Key::DPadLeft(state) => self.dpad_left = state.into(),
+            Key::DPadRight(state) => self.dpad_right = state.into(),
+            Key::TriggerLeft(state) => self.left_trigger = state.into(),
+            Key::TriggerRight(state) => self.right_trigger = state.into(),
+            Key::Select(state) if descriptor == UhidDescriptor::Xbox => {
+                self.back = state.into();
+            }
+            _ => {}
+        }
+
+        if let (Some(number), Some(state)) = (button_number(descriptor, key), key.key_event()) {
+            self.set_button(number, *state);
+        }
+    }
+
+    /// Hat direction clockwise from north (0-7), `None` when centered
+    fn hat(&self) -> Option<u8> {
+        match (
+            self.dpad_up,
+            self.dpad_right,
+            self.dpad_down,
+            self.dpad_left,
+        ) {
+            (true, false, false, false) => Some(0),
+            (true, true, false, false) => Some(1),
+            (false, true, false, false) => Some(2),
+            (false, true, true, false) => Some(3),
+            (false, false, true, false) => Some(4),
+            (false, false, true, true) => Some(5),
+            (false, false, false, true) => Some(6),
+            (true, false, false, true) => Some(7),
+            _ => None,
+        }
+    }
+
+    fn encode(&self, descriptor: UhidDescriptor) -> Vec<u8> {
+        match descriptor {
+            UhidDescriptor::Generic => {
+                let mut report = Vec::with_capacity(13);
+                report.extend_from_slice(&self.buttons.to_le_bytes());
+                report.push(self.hat().unwrap_or(8));
+                for axis in [self.left_x, self.left_y, self.right_x, self.right_y] {
+                    report.extend_from_slice(&map_signed(axis).to_le_bytes());
+                }
+                report.push(if self.left_trigger { u8::MAX } else { 0 });
+                report.push(if self.right_trigger { u8::MAX } else { 0 });
+                report
+            }
+            UhidDescriptor::Xbox => {
+                let mut report = Vec::with_capacity(17);
+                report.push(0x01);
+                for axis in [self.left_x, self.left_y, self.right_x, self.right_y] {
+                    report.extend_from_slice(&map_unsigned(axis).to_le_bytes());
+                }
+                let trigger = |pressed: bool| if pressed { 1023u16 } else { 0 };
+                report.extend_from_slice(&trigger(self.left_trigger).to_le_bytes());
+                report.extend_from_slice(&trigger(self.right_trigger).to_le_bytes());
+                report.push(self.hat().map(|hat| hat + 1).unwrap_or(0));
+                report.extend_from_slice(&(self.buttons & 0x7fff).to_le_bytes());
+                report.push(u8::from(self.back));
+                report
+            }
+        }
+    }
+}
+
+/// Button usage `key` is reported as in `descriptor`'s layout, None for keys
+/// that aren't buttons there
+fn button_number(descriptor: UhidDescriptor, key: Key) -> Option<u16> {
+    match descriptor {
+        // The kernel's gamepad usage mapping, so `hid-generic` reports the
+        // same `BTN_*` codes as the uinput backend
+        UhidDescriptor::Generic => match key {
+            Key::A(_) => Some(1),
+            Key::B(_) => Some(2),
+            Key::Y(_) => Some(4),
+            Key::X(_) => Some(5),
+            Key::BumperLeft(_) => Some(7),
+            Key::BumperRight(_) => Some(8),
+            Key::TriggerLeft(_) => Some(9),
+            Key::TriggerRight(_) => Some(10),
+            Key::Select(_) => Some(11),
+            Key::Start(_) => Some(12),
+            Key::Mode(_) => Some(13),
+            Key::ThumbLeft(_) => Some(14),
+            Key::ThumbRight(_) => Some(15),
+            _ => None,
+        },
+        // What a real 045e:02fd sends and `hid-microsoft` and SDL read. View
+        // is the AC Back bit and the triggers are only analog
+        UhidDescriptor::Xbox => match key {
+            Key::A(_) => Some(1),
+            Key::B(_) => Some(2),
+            Key::X(_) => Some(4),
+            Key::Y(_) => Some(5),
+            Key::BumperLeft(_) => Some(7),
+            Key::BumperRight(_) => Some(8),
+            Key::Start(_) => Some(12),
+            Key::Mode(_) => Some(13),
+            Key::ThumbLeft(_) => Some(14),
+            Key::ThumbRight(_) => Some(15),
+            _ => None,
+        },
+    }
+}
+
+pub struct Controller {
+    file: File,
+    descriptor: UhidDescriptor,
+    report: Report,
+    /// Last input report sent, which GET_REPORT requests are answered with
+    last_report: Arc<Mutex<Vec<u8>>>,
+    /// Stops the event reader
+    closed: Arc<AtomicBool>,
+}
+
+impl Controller {
+    pub fn new(device_name: &str, descriptor: UhidDescriptor) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(UHID_PATH)
+            .with_context(|| format!("Failed to open {UHID_PATH}"))?;
+
+        let last_report = Arc::new(Mutex::new(Vec::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+        let events = file
+            .try_clone()
+            .with_context(|| format!("Failed to duplicate {UHID_PATH}"))?;
+        spawn_event_reader(events, last_report.clone(), closed.clone())?;
+
+        let mut controller = Self {
+            file,
+            descriptor,
+            report: Report::default(),
+            last_report,
+            closed,
+        };
+        controller.create(device_name)?;
+
+        Ok(controller)
+    }
+
+    fn create(&mut self, device_name: &str) -> anyhow::Result<()> {
+        let rd_data = self.descriptor.report_descriptor();
+        let (vendor, product) = self.descriptor.vendor_product();
+
+        let mut event = UhidEvent::new(UHID_CREATE2);
+        event
+            .put_str(UHID_NAME_LEN, device_name)
+            .put_str(UHID_PHYS_LEN, "droidpad-gamepad")
+            .put_str(UHID_UNIQ_LEN, device_name)
+            .put(&(rd_data.len() as u16).to_ne_bytes())
+            .put(&self.descriptor.bus().to_ne_bytes())
+            .put(&vendor.to_ne_bytes())
+            .put(&product.to_ne_bytes())
+            .put(&0u32.to_ne_bytes())
+            .put(&0u32.to_ne_bytes())
+            .put(rd_data);
+
+        self.send(&event).context("Failed to create uhid device")
+    }
+
+    fn send(&mut self, event: &UhidEvent) -> anyhow::Result<()> {
+        write_event(&self.file, event)
+    }
+}
+
+fn write_event(mut file: &File, event: &UhidEvent) -> anyhow::Result<()> {
+    let written = file.write(&event.buffer)?;
+    if written != event.buffer.len() {
+        return Err(anyhow!(
+            "Short write to {UHID_PATH}: {written} of {} bytes",
+            event.buffer.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Drains the events the kernel sends for the device and answers its
+/// GET_REPORT and SET_REPORT requests, which drivers such as `hid-microsoft`
+/// make while binding and would otherwise wait on until they time out
+fn spawn_event_reader(
+    file: File,
+    last_report: Arc<Mutex<Vec<u8>>>,
+    closed: Arc<AtomicBool>,
+) -> anyhow::Result<()> {
+    thread::Builder::new()
+        .name(String::from("uhid-events"))
+        .spawn(move || {
+            let mut buffer = vec![0; UHID_EVENT_SIZE];
+            while !closed.load(Ordering::Relaxed) {
+                match wait_readable(&file) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        warn!("Failed to wait for uhid events: {err}");
+                        return;
+                    }
+                }
+                let read = match (&file).read(&mut buffer) {
+                    Ok(read) if read >= 4 => read,
+                    Ok(_) => continue,
+                    Err(err) => {
+                        // Reads fail once the device is destroyed
+                        if !closed.load(Ordering::Relaxed) {
+                            warn!("Failed to read uhid events: {err}");
+                        }
+                        return;
+                    }
+                };
+                if let Err(err) = answer_event(&file, &buffer[..read], &last_report) {
+                    warn!("Failed to answer uhid event: {err}");
+                }
+            }
+        })
+        .context("Failed to start the uhid event reader")?;
+
+    Ok(())
+}
+
+/// Waits up to `EVENT_POLL_MS` for an event, true when one is ready
+fn wait_readable(file: &File) -> io::Result<bool> {
+    let mut poll_fd = libc::pollfd {
+        fd: file.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: `poll_fd` is a single valid pollfd that outlives the call
+    match unsafe { libc::poll(&mut poll_fd, 1, EVENT_POLL_MS) } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+fn answer_event(file: &File, event: &[u8], last_report: &Mutex<Vec<u8>>) -> anyhow::Result<()> {
+    let event_type = u32::from_ne_bytes([event[0], event[1], event[2], event[3]]);
+    let request_id = || -> anyhow::Result<[u8; 4]> {
+        event
+            .get(4..8)
+            .and_then(|id| id.try_into().ok())
+            .ok_or_else(|| anyhow!("Truncated uhid event {event_type}"))
+    };
+
+    match event_type {
+        UHID_GET_REPORT => {
+            let id = request_id()?;
+            let report_type = event.get(9).copied().unwrap_or_default();
+            let report = last_report
+                .lock()
+                .map(|report| report.clone())
+                .unwrap_or_default();
+
+            // Only input reports exist, anything else is answered with an
+            // error rather than left to time out
+            let mut reply = UhidEvent::new(UHID_GET_REPORT_REPLY);
+            reply.put(&id);
+            if report_type == UHID_INPUT_REPORT && !report.is_empty() {
+                reply
+                    .put(&0u16.to_ne_bytes())
+                    .put(&(report.len() as u16).to_ne_bytes())
+                    .put(&report);
+            } else {
+                reply.put(&(libc::EIO as u16).to_ne_bytes());
+            }
+            write_event(file, &reply)
+        }
+        UHID_SET_REPORT => {
+            // Output and feature reports, such as rumble, aren't used
+            let mut reply = UhidEvent::new(UHID_SET_REPORT_REPLY);
+            reply.put(&request_id()?).put(&0u16.to_ne_bytes());
+            write_event(file, &reply)
+        }
+        UHID_START | UHID_STOP | UHID_OPEN | UHID_CLOSE | UHID_OUTPUT => Ok(()),
+        event_type => {
+            debug!("Ignoring uhid event {event_type}");
+            Ok(())
+        }
+    }
+}
+
+impl ControllerBackend for Controller {
+    fn write_input(&mut self, key: Key) -> anyhow::Result<()> {
+        self.report.update(key, self.descriptor);
+        Ok(())
+    }
+
+    fn synchronize(&mut self) -> anyhow::Result<()> {
+        let report = self.report.encode(self.descriptor);
+
+        let mut event = UhidEvent::new(UHID_INPUT2);
+        event.put(&(report.len() as u16).to_ne_bytes()).put(&report);
+        self.send(&event)?;
+
+        if let Ok(mut last_report) = self.last_report.lock() {
+            *last_report = report;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Controller {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        let _ = self.send(&UhidEvent::new(UHID_DESTROY));
+    }
+}
+
+struct UhidEvent {
+    buffer: Vec<u8>,
+    offset: usize,
+}
+
+impl UhidEvent {
+    fn new(event_type: u32) -> Self {
+        let mut buffer = vec![0; UHID_EVENT_SIZE];
+        buffer[..4].copy_from_slice(&event_type.to_ne_bytes());
+        Self { buffer, offset: 4 }
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buffer[self.offset..self.offset + bytes.len()].copy_from_slice(bytes);
+        self.offset += bytes.len();
+        self
+    }
+
+    /// Writes a NUL-terminated string into a fixed size field
+    fn put_str(&mut self, len: usize, value: &str) -> &mut Self {
+        let bytes = value.as_bytes();
+        let copied = bytes.len().min(len - 1);
+        self.buffer[self.offset..self.offset + copied].copy_from_slice(&bytes[..copied]);
+        self.offset += len;
+        self
+    }
+}
+
+fn map_signed(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+fn map_unsigned(value: f32) -> u16 {
+    (((value.clamp(-1.0, 1.0) + 1.0) / 2.0) * u16::MAX as f32).round() as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(descriptor: UhidDescriptor, keys: &[Key]) -> Vec<u8> {
+        let mut report = Report::default();
+        for key in keys {
+            report.update(*key, descriptor);
+        }
+        report.encode(descriptor)
+    }
+
+    #[test]
+    fn generic_layout_follows_kernel_numbering() {
+        let report = encoded(
+            UhidDescriptor::Generic,
+            &[Key::X(KeyEvent::Press), Key::Select(KeyEvent::Press)],
+        );
+        assert_eq!(u16::from_le_bytes([report[0], report[1]]), 1 << 4 | 1 << 10);
+    }
+
+    #[test]
+    fn xbox_layout_matches_a_real_02fd() {
+        let report = encoded(
+            UhidDescriptor::Xbox,
+            &[
+                Key::X(KeyEvent::Press),
+                Key::Y(KeyEvent::Press),
+                Key::TriggerLeft(KeyEvent::Press),
+                Key::Select(KeyEvent::Press),
+            ],
+        );
+        assert_eq!(report.len(), 17);
+        assert_eq!(u16::from_le_bytes([report[14], report[15]]), 0x08 | 0x10);
+        assert_eq!(u16::from_le_bytes([report[9], report[10]]), 1023);
+        assert_eq!(report[16], 1);
+    }
+}
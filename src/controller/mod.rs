@@ -10,7 +10,242 @@ mod windows;
 #[cfg(target_os = "windows")]
 pub use windows::*;
 
-use crate::input::KeyEvent;
+mod null;
+mod recording;
+mod shared;
+
+pub use shared::AxisPolicy;
+
+use std::{fmt, sync::RwLock};
+
+use anyhow::anyhow;
+
+use crate::input::{Key, KeyEvent};
+
+/// A virtual controller implementation that turns `Key`s into device output
+pub trait ControllerBackend: Send {
+    fn write_input(&mut self, key: Key) -> anyhow::Result<()>;
+    fn synchronize(&mut self) -> anyhow::Result<()>;
+
+    /// Shows the player slot on the device, for backends with player LEDs
+    fn set_player_index(&mut self, _index: u8) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Releases every button and centers every axis this connection set
+    fn neutralize(&mut self, capabilities: &Capabilities) -> anyhow::Result<()> {
+        for key in Key::ALL {
+            if capabilities.supports(key) {
+                self.write_input(key)?;
+            }
+        }
+        self.synchronize()
+    }
+}
+
+pub type BackendConstructor = fn(&str, &Options) -> anyhow::Result<Box<dyn ControllerBackend>>;
+
+/// Set of `Key` variants, ignoring their values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeySet(u32);
+
+impl KeySet {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub fn of(keys: &[Key]) -> Self {
+        keys.iter().fold(Self::empty(), |set, key| set.with(*key))
+    }
+
+    pub fn with(self, key: Key) -> Self {
+        Self(self.0 | 1 << u8::from(key))
+    }
+
+    pub fn contains(self, key: Key) -> bool {
+        self.0 & 1 << u8::from(key) != 0
+    }
+
+    pub fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+}
+
+/// What a backend can actually output
+#[derive(Clone, Copy, Debug)]
+pub struct Capabilities {
+    pub buttons: KeySet,
+    pub axes: KeySet,
+    pub analog_triggers: bool,
+    pub force_feedback: bool,
+    pub motion: bool,
+    pub player_leds: bool,
+}
+
+impl Capabilities {
+    /// Every button and axis a standard gamepad has, without extras
+    pub fn gamepad() -> Self {
+        let (buttons, axes): (Vec<Key>, Vec<Key>) = Key::ALL
+            .into_iter()
+            .partition(|key| key.key_event().is_some());
+
+        Self {
+            buttons: KeySet::of(&buttons),
+            axes: KeySet::of(&axes),
+            analog_triggers: false,
+            force_feedback: false,
+            motion: false,
+            player_leds: false,
+        }
+    }
+
+    /// Outputs nothing at all
+    pub fn none() -> Self {
+        Self {
+            buttons: KeySet::empty(),
+            axes: KeySet::empty(),
+            analog_triggers: false,
+            force_feedback: false,
+            motion: false,
+            player_leds: false,
+        }
+    }
+
+    pub fn supports(&self, key: Key) -> bool {
+        if key.key_event().is_some() {
+            self.buttons.contains(key)
+        } else {
+            self.axes.contains(key)
+        }
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        write!(
+            f,
+            "{} buttons, {} axes, analog triggers: {}, force feedback: {}, motion: {}, player LEDs: {}",
+            self.buttons.len(),
+            self.axes.len(),
+            yes_no(self.analog_triggers),
+            yes_no(self.force_feedback),
+            yes_no(self.motion),
+            yes_no(self.player_leds)
+        )
+    }
+}
+
+pub struct BackendRegistration {
+    pub backend: Backend,
+    pub capabilities: Capabilities,
+    pub create: BackendConstructor,
+}
+
+static REGISTRY: RwLock<Vec<BackendRegistration>> = RwLock::new(Vec::new());
+
+/// Registers a backend implementation, replacing any earlier registration for
+/// the same `Backend`
+pub fn register_backend(registration: BackendRegistration) -> anyhow::Result<()> {
+    let mut registry = REGISTRY
+        .write()
+        .map_err(|_| anyhow!("Failed to lock controller backend registry"))?;
+    registry.retain(|existing| existing.backend != registration.backend);
+    registry.push(registration);
+    Ok(())
+}
+
+/// Registers the backends built into this platform. Runs once on startup,
+/// before any controller is created
+pub fn register_builtin_backends() -> anyhow::Result<()> {
+    let headless = [
+        BackendRegistration {
+            backend: Backend::Null,
+            // Everything is discarded
+            capabilities: Capabilities::none(),
+            create: |name, _| Ok(Box::new(null::Controller::new(name)?)),
+        },
+        BackendRegistration {
+            backend: Backend::Log,
+            // Every key is recorded as is, player slots included. Triggers
+            // are presses like any other button
+            capabilities: Capabilities {
+                player_leds: true,
+                ..Capabilities::gamepad()
+            },
+            create: |name, options| {
+                Ok(Box::new(recording::Controller::new(
+                    name,
+                    options.log_path.as_deref(),
+                )?))
+            },
+        },
+    ];
+
+    builtin_backends()
+        .into_iter()
+        .chain(headless)
+        .try_for_each(register_backend)
+}
+
+pub fn backend_capabilities(backend: Backend) -> Option<Capabilities> {
+    REGISTRY.read().ok().and_then(|registry| {
+        registry
+            .iter()
+            .find(|registration| registration.backend == backend)
+            .map(|registration| registration.capabilities)
+    })
+}
+
+pub struct Controller {
+    backend: Box<dyn ControllerBackend>,
+    capabilities: Capabilities,
+}
+
+impl Controller {
+    pub fn new(device_name: &str, options: &Options) -> anyhow::Result<Self> {
+        let (create, capabilities) = {
+            let registry = REGISTRY
+                .read()
+                .map_err(|_| anyhow!("Failed to lock controller backend registry"))?;
+            let registration = registry
+                .iter()
+                .find(|registration| registration.backend == options.backend)
+                .ok_or_else(|| anyhow!("Backend {:?} is not registered", options.backend))?;
+            (registration.create, registration.capabilities)
+        };
+
+        Ok(Self {
+            backend: create(device_name, options)?,
+            capabilities,
+        })
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    pub fn write_input(&mut self, key: Key) -> anyhow::Result<()> {
+        self.backend.write_input(key)
+    }
+
+    pub fn synchronize(&mut self) -> anyhow::Result<()> {
+        self.backend.synchronize()
+    }
+
+    /// Releases every button and centers every axis. On a shared device only
+    /// this connection's inputs are dropped, the others' stay as they are
+    pub fn neutralize(&mut self) -> anyhow::Result<()> {
+        self.backend.neutralize(&self.capabilities)
+    }
+
+    pub fn set_player_index(&mut self, index: u8) -> anyhow::Result<()> {
+        if !self.capabilities.player_leds {
+            return Ok(());
+        }
+        self.backend.set_player_index(index)
+    }
+}
 
 #[derive(Debug, Default)]
 pub enum KeyState {
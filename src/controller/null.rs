@@ -0,0 +1,21 @@
+use crate::{controller::ControllerBackend, input::Key};
+
+/// Accepts and discards all input, so the server can run without any kernel
+/// or driver access
+pub struct Controller;
+
+impl Controller {
+    pub fn new(_device_name: &str) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+}
+
+impl ControllerBackend for Controller {
+    fn write_input(&mut self, _key: Key) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn synchronize(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
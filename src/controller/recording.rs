@@ -0,0 +1,80 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+
+use crate::{controller::ControllerBackend, input::Key};
+
+/// Records every write and synchronize call with timestamps instead of
+/// driving a real device
+pub struct Controller {
+    device_name: String,
+    writer: Box<dyn Write + Send>,
+    created_at: Instant,
+}
+
+impl Controller {
+    pub fn new(device_name: &str, path: Option<&Path>) -> anyhow::Result<Self> {
+        let writer: Box<dyn Write + Send> = match path {
+            Some(path) => Box::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open controller log {path:?}"))?,
+            ),
+            None => Box::new(io::stdout()),
+        };
+
+        let mut controller = Self {
+            device_name: device_name.to_string(),
+            writer,
+            created_at: Instant::now(),
+        };
+        controller.record("create")?;
+
+        Ok(controller)
+    }
+
+    fn record(&mut self, event: &str) -> anyhow::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let elapsed = self.created_at.elapsed().as_micros();
+
+        writeln!(
+            self.writer,
+            "{timestamp} +{elapsed}us {} {event}",
+            self.device_name
+        )?;
+        Ok(())
+    }
+}
+
+impl ControllerBackend for Controller {
+    fn write_input(&mut self, key: Key) -> anyhow::Result<()> {
+        self.record(&format!("write {key:?}"))
+    }
+
+    fn synchronize(&mut self) -> anyhow::Result<()> {
+        self.record("synchronize")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn set_player_index(&mut self, index: u8) -> anyhow::Result<()> {
+        self.record(&format!("player {index}"))
+    }
+}
+
+impl Drop for Controller {
+    fn drop(&mut self) {
+        let _ = self.record("destroy");
+        let _ = self.writer.flush();
+    }
+}
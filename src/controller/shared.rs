@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex, Weak},
+};
+
+use anyhow::anyhow;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use super::{Capabilities, Controller, ControllerBackend, Options};
+use crate::input::{Key, KeyEvent};
+
+/// How axis values from several connections sharing one device are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+pub enum AxisPolicy {
+    /// The most recent write wins
+    #[default]
+    LastWriter,
+    /// The value furthest from center wins
+    LargestMagnitude,
+}
+
+static SHARED_DEVICES: LazyLock<Mutex<HashMap<String, Weak<Mutex<SharedDevice>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct SharedDevice {
+    name: String,
+    controller: Controller,
+    policy: AxisPolicy,
+    next_contributor: u64,
+    next_write: u64,
+    /// Latest key per key id for every contributor, with a write sequence number
+    contributions: HashMap<u64, HashMap<u8, (u64, Key)>>,
+}
+
+impl SharedDevice {
+    fn write(&mut self, contributor: u64, key: Key) -> anyhow::Result<()> {
+        self.next_write += 1;
+        self.contributions
+            .entry(contributor)
+            .or_default()
+            .insert(key.into(), (self.next_write, key));
+
+        let merged = self.merged(key);
+        self.controller.write_input(merged)
+    }
+
+    /// Combines every contributor's latest value for `key`. Buttons are OR-ed,
+    /// axes follow the device's `AxisPolicy`
+    fn merged(&self, key: Key) -> Key {
+        let id = u8::from(key);
+        let mut latest = self
+            .contributions
+            .values()
+            .filter_map(|keys| keys.get(&id).copied())
+            .collect::<Vec<_>>();
+        latest.sort_by_key(|(sequence, _)| *sequence);
+
+        if key.key_event().is_some() {
+            return latest
+                .iter()
+                .map(|(_, key)| *key)
+                .find(|key| key.key_event() == Some(&KeyEvent::Press))
+                .unwrap_or(key.neutral());
+        }
+
+        match self.policy {
+            AxisPolicy::LastWriter => latest.last().map(|(_, key)| *key),
+            AxisPolicy::LargestMagnitude => latest.iter().map(|(_, key)| *key).max_by(|a, b| {
+                let a = a.axis_value().unwrap_or_default().abs();
+                let b = b.axis_value().unwrap_or_default().abs();
+                a.total_cmp(&b)
+            }),
+        }
+        .unwrap_or(key.neutral())
+    }
+
+    /// Drops every input `contributor` set from the merge, leaving the
+    /// others' as they are
+    fn clear(&mut self, contributor: u64) -> anyhow::Result<()> {
+        let Some(keys) = self.contributions.get_mut(&contributor) else {
+            return Ok(());
+        };
+
+        for (_, key) in std::mem::take(keys).into_values() {
+            let merged = self.merged(key);
+            self.controller.write_input(merged)?;
+        }
+        self.controller.synchronize()
+    }
+
+    fn remove(&mut self, contributor: u64) -> anyhow::Result<()> {
+        let cleared = self.clear(contributor);
+        self.contributions.remove(&contributor);
+        cleared
+    }
+}
+
+/// One connection's view of a shared device
+struct SharedHandle {
+    contributor: u64,
+    device: Arc<Mutex<SharedDevice>>,
+}
+
+impl SharedHandle {
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, SharedDevice>> {
+        self.device
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock shared controller"))
+    }
+}
+
+impl ControllerBackend for SharedHandle {
+    fn write_input(&mut self, key: Key) -> anyhow::Result<()> {
+        self.lock()?.write(self.contributor, key)
+    }
+
+    fn synchronize(&mut self) -> anyhow::Result<()> {
+        self.lock()?.controller.synchronize()
+    }
+
+    /// Writing neutral values would override what the other connections hold,
+    /// so this connection's inputs are dropped from the merge instead
+    fn neutralize(&mut self, _capabilities: &Capabilities) -> anyhow::Result<()> {
+        self.lock()?.clear(self.contributor)
+    }
+}
+
+impl Drop for SharedHandle {
+    fn drop(&mut self) {
+        if let Ok(mut device) = self.device.lock() {
+            let _ = device.remove(self.contributor);
+            info!(
+                "Connection left shared controller {}, {} remaining",
+                device.name,
+                device.contributions.len()
+            );
+        }
+    }
+}
+
+impl Controller {
+    /// Joins the shared device called `device_name`, creating it on first use.
+    /// The device is destroyed once every connection using it is dropped
+    pub fn shared(
+        device_name: &str,
+        options: &Options,
+        policy: AxisPolicy,
+    ) -> anyhow::Result<Self> {
+        let mut devices = SHARED_DEVICES
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock shared controllers"))?;
+        devices.retain(|_, device| device.strong_count() > 0);
+
+        let device = match devices.get(device_name).and_then(Weak::upgrade) {
+            Some(device) => device,
+            None => {
+                let device = Arc::new(Mutex::new(SharedDevice {
+                    name: device_name.to_string(),
+                    controller: Controller::new(device_name, options)?,
+                    policy,
+                    next_contributor: 0,
+                    next_write: 0,
+                    contributions: HashMap::new(),
+                }));
+                devices.insert(device_name.to_string(), Arc::downgrade(&device));
+                info!("Created shared controller {device_name}");
+                device
+            }
+        };
+
+        let (contributor, capabilities) = {
+            let mut shared = device
+                .lock()
+                .map_err(|_| anyhow!("Failed to lock shared controller"))?;
+            shared.next_contributor += 1;
+            let contributor = shared.next_contributor;
+            shared.contributions.insert(contributor, HashMap::new());
+            (contributor, shared.controller.capabilities)
+        };
+
+        Ok(Self {
+            backend: Box::new(SharedHandle {
+                contributor,
+                device,
+            }),
+            capabilities,
+        })
+    }
+}
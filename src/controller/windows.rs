@@ -4,7 +4,9 @@ mod vjoy;
 #[cfg(feature = "vigem")]
 mod vigembus;
 
-use crate::input::Key;
+use std::path::PathBuf;
+
+use super::{BackendRegistration, Capabilities};
 
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +19,11 @@ pub struct Options {
     #[arg(long, default_value_t = 0)]
     /// Sets the vjoy device to use when `--backend vjoy` is selected
     pub vjoy_device: u8,
+
+    /// Sets the file that `--backend log` appends to (stdout when unset)
+    #[arg(long)]
+    #[serde(default)]
+    pub log_path: Option<PathBuf>,
 }
 
 impl Default for Options {
@@ -25,6 +32,7 @@ impl Default for Options {
             backend: Backend::default(),
             #[cfg(feature = "vjoy")]
             vjoy_device: 0,
+            log_path: None,
         }
     }
 }
@@ -46,6 +54,8 @@ pub enum Backend {
     Vigem,
     #[cfg(feature = "vjoy")]
     Vjoy,
+    Null,
+    Log,
 }
 
 #[cfg(feature = "vigem")]
@@ -62,38 +72,29 @@ impl Default for Backend {
     }
 }
 
-pub enum Controller {
-    #[cfg(feature = "vigem")]
-    Vigem(vigembus::Controller),
-    #[cfg(feature = "vjoy")]
-    Vjoy(vjoy::Controller),
-}
-
-impl Controller {
-    pub fn new(device_name: &str, options: &Options) -> anyhow::Result<Self> {
-        match options.backend {
-            #[cfg(feature = "vigem")]
-            Backend::Vigem => Ok(Self::Vigem(vigembus::Controller::new(device_name)?)),
-            #[cfg(feature = "vjoy")]
-            Backend::Vjoy => Ok(Self::Vjoy(vjoy::Controller::new(device_name)?)),
-        }
-    }
-
-    pub fn write_input(&mut self, key: Key) -> anyhow::Result<()> {
-        match self {
-            #[cfg(feature = "vigem")]
-            Self::Vigem(controller) => controller.write_input(key),
-            #[cfg(feature = "vjoy")]
-            Self::Vjoy(controller) => controller.write_input(key),
-        }
+#[cfg(not(any(feature = "vigem", feature = "vjoy")))]
+impl Default for Backend {
+    fn default() -> Self {
+        return Backend::Null;
     }
+}
 
-    pub fn synchronize(&mut self) -> anyhow::Result<()> {
-        match self {
-            #[cfg(feature = "vigem")]
-            Self::Vigem(controller) => controller.synchronize(),
-            #[cfg(feature = "vjoy")]
-            Self::Vjoy(controller) => controller.synchronize(),
-        }
-    }
+pub(super) fn builtin_backends() -> Vec<BackendRegistration> {
+    vec![
+        #[cfg(feature = "vigem")]
+        BackendRegistration {
+            backend: Backend::Vigem,
+            capabilities: Capabilities {
+                analog_triggers: true,
+                ..Capabilities::gamepad()
+            },
+            create: |name, _| Ok(Box::new(vigembus::Controller::new(name)?)),
+        },
+        #[cfg(feature = "vjoy")]
+        BackendRegistration {
+            backend: Backend::Vjoy,
+            capabilities: Capabilities::gamepad(),
+            create: |name, _| Ok(Box::new(vjoy::Controller::new(name)?)),
+        },
+    ]
 }
@@ -4,7 +4,10 @@ use std::thread;
 use anyhow::anyhow;
 use vigem_rust::{Client, TargetHandle, X360Button, X360Report, target::Xbox360};
 
-use crate::input::{Key, KeyEvent};
+use crate::{
+    controller::ControllerBackend,
+    input::{Key, KeyEvent},
+};
 
 static VIGEM: LazyLock<Result<Mutex<Client>, String>> = LazyLock::new(|| {
     Client::connect()
@@ -41,7 +44,10 @@ impl Controller {
             report: X360Report::default(),
         })
     }
-    pub fn write_input(&mut self, key: Key) -> anyhow::Result<()> {
+}
+
+impl ControllerBackend for Controller {
+    fn write_input(&mut self, key: Key) -> anyhow::Result<()> {
         match key {
             Key::LeftJoystickX(v) => self.report.thumb_lx = map_vigem(v),
             Key::LeftJoystickY(v) => self.report.thumb_ly = map_vigem(v),
@@ -93,7 +99,7 @@ impl Controller {
         Ok(())
     }
 
-    pub fn synchronize(&self) -> anyhow::Result<()> {
+    fn synchronize(&mut self) -> anyhow::Result<()> {
         self.device.update(&self.report)?;
         Ok(())
     }
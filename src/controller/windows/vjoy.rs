@@ -3,7 +3,10 @@ use std::sync::{LazyLock, Mutex};
 use log::info;
 use vjoy::{ButtonState, Device, VJoy};
 
-use crate::input::{Key, KeyEvent};
+use crate::{
+    controller::ControllerBackend,
+    input::{Key, KeyEvent},
+};
 use anyhow::anyhow;
 
 pub struct Controller {
@@ -45,7 +48,10 @@ impl Controller {
         *device_id += 1;
         Ok(Self { device })
     }
-    pub fn write_input(&mut self, key: Key) -> anyhow::Result<()> {
+}
+
+impl ControllerBackend for Controller {
+    fn write_input(&mut self, key: Key) -> anyhow::Result<()> {
         let t: (u8, Value) = key.into();
         match t {
             (axis, Value::Axis(v)) => self.device.set_axis(axis as u32, v),
@@ -55,7 +61,7 @@ impl Controller {
         Ok(())
     }
 
-    pub fn synchronize(&mut self) -> anyhow::Result<()> {
+    fn synchronize(&mut self) -> anyhow::Result<()> {
         let vjoy = VJOY
             .as_ref()
             .map_err(|err| anyhow!("Failed to init vjoy: {err}"))?;
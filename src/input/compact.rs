@@ -0,0 +1,170 @@
+use std::borrow::Cow;
+
+use anyhow::anyhow;
+
+use super::{KeyEvent, Message};
+
+/// WebSocket subprotocol a client asks for to send compact frames
+pub const PROTOCOL: &str = "droidpad.compact.v1";
+/// First byte of every compact frame
+pub const VERSION: u8 = 1;
+
+/// Decodes every update in a compact frame, in order. A frame is the
+/// `VERSION` byte followed by control updates, each a control id whose top
+/// bits give the size of the value after it:
+/// - `0x00..=0x7F`: one byte, 0 for release and 1 for press
+/// - `0x80..=0xBF`: two little-endian `i16` axes, x then y
+///
+/// Ids from `0xC0` up are reserved. Updates for ids this server doesn't know
+/// are skipped, so clients can send controls added later. Nothing is added to
+/// `messages` unless the whole frame decodes
+pub fn decode(frame: &[u8], messages: &mut impl Extend<Message>) -> anyhow::Result<()> {
+    let Some((&version, mut rest)) = frame.split_first() else {
+        return Err(anyhow!("Empty compact frame"));
+    };
+    if version != VERSION {
+        return Err(anyhow!("Unsupported compact protocol version {version}"));
+    }
+
+    let mut decoded = Vec::new();
+    while let Some((&control, tail)) = rest.split_first() {
+        let size = match control {
+            0x00..=0x7F => 1,
+            0x80..=0xBF => 4,
+            0xC0..=0xFF => return Err(anyhow!("Reserved control id {control:#04x}")),
+        };
+        let Some((value, tail)) = tail.split_at_checked(size) else {
+            return Err(anyhow!("Compact frame ends inside control {control:#04x}"));
+        };
+        rest = tail;
+
+        let message = match size {
+            1 => button(control, value[0])?,
+            _ => stick(control, axis(&value[..2]), axis(&value[2..])),
+        };
+        decoded.extend(message);
+    }
+
+    messages.extend(decoded);
+    Ok(())
+}
+
+fn button(control: u8, state: u8) -> anyhow::Result<Option<Message>> {
+    let state = match state {
+        0 => KeyEvent::Release,
+        1 => KeyEvent::Press,
+        _ => return Err(anyhow!("Invalid state {state} for control {control:#04x}")),
+    };
+    let id = match control {
+        0x01 => "A",
+        0x02 => "B",
+        0x03 => "X",
+        0x04 => "Y",
+        0x05 => "lb",
+        0x06 => "lt",
+        0x07 => "rb",
+        0x08 => "rt",
+        0x09 => "start",
+        0x0A => "back",
+        0x0B => "thumb_left",
+        0x0C => "thumb_right",
+        0x10..=0x13 => {
+            let button = ["UP", "DOWN", "LEFT", "RIGHT"][usize::from(control - 0x10)];
+            return Ok(Some(Message::Dpad {
+                id: Cow::Borrowed("dpad"),
+                button: Cow::Borrowed(button),
+                state,
+            }));
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(Message::Button {
+        id: Cow::Borrowed(id),
+        state,
+    }))
+}
+
+fn stick(control: u8, x: f32, y: f32) -> Option<Message> {
+    let id = match control {
+        0x80 => "left",
+        0x81 => "right",
+        _ => return None,
+    };
+    Some(Message::Joystick {
+        id: Cow::Borrowed(id),
+        x,
+        y,
+    })
+}
+
+/// Maps `-32767..=32767` onto `-1.0..=1.0`
+fn axis(bytes: &[u8]) -> f32 {
+    let value = i16::from_le_bytes([bytes[0], bytes[1]]);
+    (f32::from(value) / f32::from(i16::MAX)).clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoded(frame: &[u8]) -> anyhow::Result<Vec<Message>> {
+        let mut messages = Vec::new();
+        decode(frame, &mut messages)?;
+        Ok(messages)
+    }
+
+    #[test]
+    fn decodes_buttons_dpad_and_sticks() {
+        let messages = decoded(&[VERSION, 0x01, 1, 0x12, 0, 0x81, 0xFF, 0x7F, 0x01, 0x80]).unwrap();
+        assert!(matches!(
+            &messages[..],
+            [
+                Message::Button { id, state: KeyEvent::Press },
+                Message::Dpad { button, state: KeyEvent::Release, .. },
+                Message::Joystick { id: stick, x, y },
+            ] if id == "A" && button == "LEFT" && stick == "right" && *x == 1.0 && *y == -1.0
+        ));
+    }
+
+    #[test]
+    fn clamps_the_lowest_axis_value() {
+        let messages = decoded(&[VERSION, 0x80, 0x00, 0x80, 0, 0]).unwrap();
+        assert!(matches!(
+            &messages[..],
+            [Message::Joystick { x, y, .. }] if *x == -1.0 && *y == 0.0
+        ));
+    }
+
+    #[test]
+    fn skips_unknown_controls() {
+        let messages = decoded(&[VERSION, 0x7F, 1, 0xBF, 1, 2, 3, 4, 0x02, 1]).unwrap();
+        assert!(matches!(&messages[..], [Message::Button { id, .. }] if id == "B"));
+    }
+
+    #[test]
+    fn accepts_empty_frames() {
+        assert!(decoded(&[VERSION]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert!(decoded(&[]).is_err());
+        assert!(decoded(&[2, 0x01, 1]).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_and_reserved_updates() {
+        assert!(decoded(&[VERSION, 0x01]).is_err());
+        assert!(decoded(&[VERSION, 0x80, 0, 0, 0]).is_err());
+        assert!(decoded(&[VERSION, 0xC0, 0, 0]).is_err());
+        assert!(decoded(&[VERSION, 0x01, 2]).is_err());
+    }
+
+    #[test]
+    fn adds_nothing_from_a_broken_frame() {
+        let mut messages = Vec::new();
+        assert!(decode(&[VERSION, 0x01, 1, 0x02, 1, 0x80, 0], &mut messages).is_err());
+        assert!(messages.is_empty());
+    }
+}
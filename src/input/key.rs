@@ -1,6 +1,6 @@
 use crate::input::KeyEvent;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum Key {
     LeftJoystickX(f32),
     LeftJoystickY(f32),
@@ -26,6 +26,68 @@ pub enum Key {
 }
 
 impl Key {
+    /// Every key, released or centered
+    pub const ALL: [Key; 21] = [
+        Key::LeftJoystickX(0.0),
+        Key::LeftJoystickY(0.0),
+        Key::RightJoystickX(0.0),
+        Key::RightJoystickY(0.0),
+        Key::ThumbRight(KeyEvent::Release),
+        Key::ThumbLeft(KeyEvent::Release),
+        Key::DPadUp(KeyEvent::Release),
+        Key::DPadDown(KeyEvent::Release),
+        Key::DPadLeft(KeyEvent::Release),
+        Key::DPadRight(KeyEvent::Release),
+        Key::A(KeyEvent::Release),
+        Key::B(KeyEvent::Release),
+        Key::X(KeyEvent::Release),
+        Key::Y(KeyEvent::Release),
+        Key::Start(KeyEvent::Release),
+        Key::Select(KeyEvent::Release),
+        Key::TriggerLeft(KeyEvent::Release),
+        Key::BumperLeft(KeyEvent::Release),
+        Key::TriggerRight(KeyEvent::Release),
+        Key::BumperRight(KeyEvent::Release),
+        Key::Mode(KeyEvent::Release),
+    ];
+
+    /// The same key released, or centered for axes
+    pub fn neutral(self) -> Self {
+        match self {
+            Key::LeftJoystickX(_) => Key::LeftJoystickX(0.0),
+            Key::LeftJoystickY(_) => Key::LeftJoystickY(0.0),
+            Key::RightJoystickX(_) => Key::RightJoystickX(0.0),
+            Key::RightJoystickY(_) => Key::RightJoystickY(0.0),
+            Key::ThumbRight(_) => Key::ThumbRight(KeyEvent::Release),
+            Key::ThumbLeft(_) => Key::ThumbLeft(KeyEvent::Release),
+            Key::DPadUp(_) => Key::DPadUp(KeyEvent::Release),
+            Key::DPadDown(_) => Key::DPadDown(KeyEvent::Release),
+            Key::DPadLeft(_) => Key::DPadLeft(KeyEvent::Release),
+            Key::DPadRight(_) => Key::DPadRight(KeyEvent::Release),
+            Key::A(_) => Key::A(KeyEvent::Release),
+            Key::B(_) => Key::B(KeyEvent::Release),
+            Key::X(_) => Key::X(KeyEvent::Release),
+            Key::Y(_) => Key::Y(KeyEvent::Release),
+            Key::Start(_) => Key::Start(KeyEvent::Release),
+            Key::Select(_) => Key::Select(KeyEvent::Release),
+            Key::TriggerLeft(_) => Key::TriggerLeft(KeyEvent::Release),
+            Key::BumperLeft(_) => Key::BumperLeft(KeyEvent::Release),
+            Key::TriggerRight(_) => Key::TriggerRight(KeyEvent::Release),
+            Key::BumperRight(_) => Key::BumperRight(KeyEvent::Release),
+            Key::Mode(_) => Key::Mode(KeyEvent::Release),
+        }
+    }
+
+    pub fn axis_value(&self) -> Option<f32> {
+        match self {
+            Key::LeftJoystickX(v)
+            | Key::LeftJoystickY(v)
+            | Key::RightJoystickX(v)
+            | Key::RightJoystickY(v) => Some(*v),
+            _ => None,
+        }
+    }
+
     pub fn key_event(&self) -> Option<&KeyEvent> {
         match self {
             Key::LeftJoystickX(_) => None,
@@ -0,0 +1,61 @@
+use crate::input::{Key, KeyEvent};
+
+/// Every key the DroidPad message mapping can produce
+pub const BINDINGS: &[Key] = &[
+    Key::LeftJoystickX(0.0),
+    Key::LeftJoystickY(0.0),
+    Key::RightJoystickX(0.0),
+    Key::RightJoystickY(0.0),
+    Key::DPadUp(KeyEvent::Release),
+    Key::DPadDown(KeyEvent::Release),
+    Key::DPadLeft(KeyEvent::Release),
+    Key::DPadRight(KeyEvent::Release),
+    Key::A(KeyEvent::Release),
+    Key::B(KeyEvent::Release),
+    Key::X(KeyEvent::Release),
+    Key::Y(KeyEvent::Release),
+    Key::BumperLeft(KeyEvent::Release),
+    Key::TriggerLeft(KeyEvent::Release),
+    Key::BumperRight(KeyEvent::Release),
+    Key::TriggerRight(KeyEvent::Release),
+    Key::Start(KeyEvent::Release),
+    Key::Select(KeyEvent::Release),
+    Key::ThumbRight(KeyEvent::Release),
+    Key::ThumbLeft(KeyEvent::Release),
+];
+
+pub fn dpad_key(button: &str, state: KeyEvent) -> Option<Key> {
+    match button {
+        "LEFT" => Some(Key::DPadLeft(state)),
+        "RIGHT" => Some(Key::DPadRight(state)),
+        "UP" => Some(Key::DPadUp(state)),
+        "DOWN" => Some(Key::DPadDown(state)),
+        _ => None,
+    }
+}
+
+pub fn joystick_keys(id: &str, x: f32, y: f32) -> Option<[Key; 2]> {
+    match id {
+        "left" => Some([Key::LeftJoystickX(x), Key::LeftJoystickY(y)]),
+        "right" => Some([Key::RightJoystickX(x), Key::RightJoystickY(y)]),
+        _ => None,
+    }
+}
+
+pub fn button_key(id: &str, state: KeyEvent) -> Option<Key> {
+    match id {
+        "A" => Some(Key::A(state)),
+        "B" => Some(Key::B(state)),
+        "X" => Some(Key::X(state)),
+        "Y" => Some(Key::Y(state)),
+        "lb" => Some(Key::BumperLeft(state)),
+        "lt" => Some(Key::TriggerLeft(state)),
+        "rb" => Some(Key::BumperRight(state)),
+        "rt" => Some(Key::TriggerRight(state)),
+        "start" => Some(Key::Start(state)),
+        "back" => Some(Key::Select(state)),
+        "thumb_right" => Some(Key::ThumbRight(state)),
+        "thumb_left" => Some(Key::ThumbLeft(state)),
+        _ => None,
+    }
+}
@@ -1,4 +1,5 @@
-#[cfg(any(target_os = "linux", target_os = "windows"))]
+use std::{borrow::Cow, fmt};
+
 use anyhow::{Context, anyhow};
 use serde::Deserialize;
 
@@ -8,16 +9,39 @@ use serde::Deserialize;
 pub enum Message {
     #[serde(alias = "DPAD")]
     Dpad {
-        id: String,
-        button: String,
+        id: Cow<'static, str>,
+        button: Cow<'static, str>,
         state: KeyEvent,
     },
     #[serde(alias = "JOYSTICK")]
-    Joystick { id: String, x: f32, y: f32 },
+    Joystick {
+        id: Cow<'static, str>,
+        x: f32,
+        y: f32,
+    },
     #[serde(alias = "BUTTON")]
-    Button { id: String, state: KeyEvent },
+    Button {
+        id: Cow<'static, str>,
+        state: KeyEvent,
+    },
+    /// A control type this server doesn't know, e.g. from a newer DroidPad
+    #[serde(other)]
+    Unknown,
+}
+
+/// A message that arrived intact but couldn't be understood, as opposed to a
+/// connection that broke
+#[derive(Debug)]
+pub struct InvalidMessage(pub anyhow::Error);
+
+impl fmt::Display for InvalidMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
 }
 
+impl std::error::Error for InvalidMessage {}
+
 #[repr(u8)]
 #[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
 pub enum KeyEvent {
@@ -27,9 +51,40 @@ pub enum KeyEvent {
     Press = 1,
 }
 
+/// The text encodings messages arrive in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Json,
+    Csv,
+}
+
+impl Encoding {
+    /// Tells a frame's or line's encoding from how it starts
+    fn sniff(text: &str) -> Self {
+        if text.trim_start().starts_with('{') {
+            Self::Json
+        } else {
+            Self::Csv
+        }
+    }
+}
+
 impl Message {
-    #[cfg(any(target_os = "linux", target_os = "windows"))]
-    pub fn from_droidpad_csv(line: &str) -> anyhow::Result<Self> {
+    /// Decodes a frame or line in whichever encoding it turns out to use, so
+    /// every transport accepts them all
+    pub fn decode(text: &str) -> Result<Self, InvalidMessage> {
+        let message = match Encoding::sniff(text) {
+            Encoding::Json => {
+                serde_json::from_str(text).context("Failed to parse message as DroidPad JSON")
+            }
+            Encoding::Csv => {
+                Self::from_droidpad_csv(text).context("Failed to parse message as DroidPad CSV")
+            }
+        };
+        message.map_err(InvalidMessage)
+    }
+
+    fn from_droidpad_csv(line: &str) -> anyhow::Result<Self> {
         let parts: Vec<_> = line.trim().split(',').collect();
         if parts.is_empty() || parts[0].is_empty() {
             return Err(anyhow!("Empty DroidPad CSV message"));
@@ -37,27 +92,28 @@ impl Message {
 
         match parts.as_slice() {
             [id, "BUTTON", state] => Ok(Self::Button {
-                id: (*id).to_string(),
+                id: Cow::Owned((*id).to_string()),
                 state: KeyEvent::from_droidpad_csv(state)?,
             }),
             [id, "DPAD", button, state] => Ok(Self::Dpad {
-                id: (*id).to_string(),
-                button: (*button).to_string(),
+                id: Cow::Owned((*id).to_string()),
+                button: Cow::Owned((*button).to_string()),
                 state: KeyEvent::from_droidpad_csv(state)?,
             }),
             [id, "JOYSTICK", x, y] => Ok(Self::Joystick {
-                id: (*id).to_string(),
+                id: Cow::Owned((*id).to_string()),
                 x: x.parse().context("Invalid joystick x value")?,
                 y: y.parse().context("Invalid joystick y value")?,
             }),
+            // Newer DroidPad versions may add control types
+            [_, kind, ..] if !matches!(*kind, "BUTTON" | "DPAD" | "JOYSTICK") => Ok(Self::Unknown),
             _ => Err(anyhow!("Unsupported DroidPad CSV message: {line}")),
         }
     }
 }
 
 impl KeyEvent {
-    #[cfg(any(target_os = "linux", target_os = "windows"))]
-    pub fn from_droidpad_csv(value: &str) -> anyhow::Result<Self> {
+    fn from_droidpad_csv(value: &str) -> anyhow::Result<Self> {
         match value {
             "PRESS" => Ok(Self::Press),
             "RELEASE" => Ok(Self::Release),
@@ -75,3 +131,68 @@ impl From<KeyEvent> for bool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_json_and_csv() {
+        let json = Message::decode(r#"  {"type":"BUTTON","id":"A","state":"PRESS"}"#).unwrap();
+        let csv = Message::decode("A,BUTTON,PRESS").unwrap();
+        for message in [json, csv] {
+            assert!(matches!(
+                message,
+                Message::Button { id, state: KeyEvent::Press } if id == "A"
+            ));
+        }
+    }
+
+    #[test]
+    fn decodes_csv_controls() {
+        assert!(matches!(
+            Message::decode(" dpad,DPAD,UP,RELEASE\n").unwrap(),
+            Message::Dpad { id, button, state: KeyEvent::Release } if id == "dpad" && button == "UP"
+        ));
+        assert!(matches!(
+            Message::decode("left,JOYSTICK,0.5,-1").unwrap(),
+            Message::Joystick { id, x, y } if id == "left" && x == 0.5 && y == -1.0
+        ));
+    }
+
+    #[test]
+    fn decodes_json_joysticks() {
+        assert!(matches!(
+            Message::decode(r#"{"type":"JOYSTICK","id":"right","x":-0.25,"y":1.0}"#).unwrap(),
+            Message::Joystick { id, x, y } if id == "right" && x == -0.25 && y == 1.0
+        ));
+    }
+
+    #[test]
+    fn passes_unknown_control_types() {
+        assert!(matches!(
+            Message::decode(r#"{"type":"SLIDER","id":"s","value":3}"#).unwrap(),
+            Message::Unknown
+        ));
+        assert!(matches!(
+            Message::decode("s,SLIDER,3").unwrap(),
+            Message::Unknown
+        ));
+    }
+
+    #[test]
+    fn rejects_broken_messages() {
+        for text in [
+            "",
+            "A",
+            "A,BUTTON",
+            "A,BUTTON,HOLD",
+            "A,BUTTON,CLICK",
+            "left,JOYSTICK,x,0",
+            r#"{"type":"BUTTON","id":"A""#,
+            r#"{"type":"BUTTON","id":"A","state":"HOLD"}"#,
+        ] {
+            assert!(Message::decode(text).is_err(), "{text:?} decoded");
+        }
+    }
+}
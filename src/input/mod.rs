@@ -1,5 +1,8 @@
+#[cfg(feature = "ws")]
+pub mod compact;
 mod key;
+pub mod mapping;
 mod message;
 
 pub use key::Key;
-pub use message::{KeyEvent, Message};
+pub use message::{InvalidMessage, KeyEvent, Message};
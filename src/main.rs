@@ -1,31 +1,33 @@
-#[cfg(not(any(feature = "ws", feature = "bluetooth")))]
-compile_error!("At least one transport feature must be enabled: `ws` or `bluetooth`.");
+#[cfg(not(feature = "any-transport"))]
+compile_error!(
+    "At least one transport feature must be enabled: `ws`, `bluetooth`, `tcp`, `udp`, `mqtt`, `unix` or `serial`."
+);
 
-#[cfg(any(feature = "ws", feature = "bluetooth"))]
+#[cfg(feature = "any-transport")]
 mod app;
 mod controller;
 mod input;
-#[cfg(any(feature = "ws", feature = "bluetooth"))]
+#[cfg(feature = "any-transport")]
 mod transport;
-#[cfg(all(feature = "ui", any(feature = "ws", feature = "bluetooth")))]
+#[cfg(all(feature = "ui", feature = "any-transport"))]
 mod ui;
 
-#[cfg(all(not(feature = "ui"), any(feature = "ws", feature = "bluetooth")))]
+#[cfg(all(not(feature = "ui"), feature = "any-transport"))]
 use clap::Parser;
 
-#[cfg(all(not(feature = "ui"), any(feature = "ws", feature = "bluetooth")))]
+#[cfg(all(not(feature = "ui"), feature = "any-transport"))]
 use crate::app::{Args, run_cli};
 
-#[cfg(all(not(feature = "ui"), any(feature = "ws", feature = "bluetooth")))]
+#[cfg(all(not(feature = "ui"), feature = "any-transport"))]
 #[tokio::main]
 async fn main() {
     run_cli(Args::parse()).await;
 }
 
-#[cfg(all(feature = "ui", any(feature = "ws", feature = "bluetooth")))]
+#[cfg(all(feature = "ui", feature = "any-transport"))]
 fn main() {
     ui::run();
 }
 
-#[cfg(not(any(feature = "ws", feature = "bluetooth")))]
+#[cfg(not(feature = "any-transport"))]
 fn main() {}
@@ -4,23 +4,25 @@ use bluer::{
     rfcomm::{Profile, ProfileHandle, Role, Stream},
 };
 use futures_util::StreamExt;
-use log::info;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use log::{info, warn};
+use tokio::io::{AsyncWriteExt, BufReader};
 
 use crate::{
-    app::Args,
+    app::{Args, PeerAddr, check_access},
     input::Message,
-    transport::{Transport, TransportConnection},
+    transport::{Transport, TransportConnection, handshakes::Handshakes, pin_line, read_line},
 };
 
 pub struct BluetoothTransport {
     profile_handle: Option<ProfileHandle>,
+    handshakes: Handshakes<BluetoothTransportConnection>,
 }
 
 impl BluetoothTransport {
     pub fn new() -> Self {
         Self {
             profile_handle: None,
+            handshakes: Handshakes::default(),
         }
     }
 }
@@ -76,21 +78,38 @@ impl Transport for BluetoothTransport {
         Ok(())
     }
 
-    async fn accept(&mut self, _args: Args) -> anyhow::Result<Self::Connection> {
+    /// Yields the next connection that was accepted. Accepting runs in its
+    /// own task, so one stuck connection doesn't hold up the others
+    async fn accept(&mut self, args: Args) -> anyhow::Result<Self::Connection> {
         let profile_handle = self
             .profile_handle
             .as_mut()
             .ok_or_else(|| anyhow!("Bluetooth transport is not listening"))?;
-        let request = profile_handle
-            .next()
-            .await
-            .ok_or_else(|| anyhow!("Bluetooth profile connection stream ended"))?;
-        let peer_addr = request.device();
-        let stream = request
-            .accept()
-            .context("Failed to accept Bluetooth profile connection")?;
 
-        Ok(BluetoothTransportConnection::new(stream, peer_addr))
+        loop {
+            tokio::select! {
+                connection = self.handshakes.next() => return Ok(connection),
+                request = profile_handle.next(), if self.handshakes.has_room(&args) => {
+                    let request =
+                        request.ok_or_else(|| anyhow!("Bluetooth profile connection stream ended"))?;
+                    let peer_addr = request.device();
+                    if let Err(err) =
+                        check_access(&args, PeerAddr::Bluetooth(&peer_addr.to_string()))
+                    {
+                        // Dropping the request rejects it
+                        warn!("{err}");
+                        continue;
+                    }
+
+                    self.handshakes.spawn(peer_addr, &args, async move {
+                        let stream = request
+                            .accept()
+                            .context("Failed to accept Bluetooth profile connection")?;
+                        Ok(BluetoothTransportConnection::new(stream, peer_addr))
+                    });
+                }
+            }
+        }
     }
 }
 
@@ -113,22 +132,35 @@ impl TransportConnection for BluetoothTransportConnection {
         format!("droidpad-{}", self.peer_addr)
     }
 
-    async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
-        loop {
-            let mut line = String::new();
-            let bytes_read = self.reader.read_line(&mut line).await?;
-            if bytes_read == 0 {
-                return Ok(None);
-            }
+    fn peer_id(&self) -> String {
+        format!("bt:{}", self.peer_addr)
+    }
 
-            let line = line.trim_matches(|c| c == '\r' || c == '\n' || c == '\0');
-            if line.is_empty() {
-                continue;
-            }
+    fn peer_address(&self) -> Option<String> {
+        Some(self.peer_addr.to_string())
+    }
 
-            return Message::from_droidpad_csv(line)
-                .context("Failed to parse Bluetooth RFCOMM message")
-                .map(Some);
-        }
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(read_line(&mut self.reader)
+            .await?
+            .as_deref()
+            .and_then(pin_line))
+    }
+
+    async fn send_token(&mut self, token: &str) -> anyhow::Result<bool> {
+        let stream = self.reader.get_mut();
+        stream
+            .write_all(format!("token,{token}\n").as_bytes())
+            .await?;
+        stream.flush().await?;
+        Ok(true)
+    }
+
+    async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
+        let Some(line) = read_line(&mut self.reader).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Message::decode(&line)?))
     }
 }
@@ -1,5 +1,5 @@
 use anyhow::{Context, anyhow};
-use log::info;
+use log::{info, warn};
 use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
 use windows::{
     Devices::Bluetooth::Rfcomm::{RfcommServiceId, RfcommServiceProvider},
@@ -7,13 +7,13 @@ use windows::{
     Networking::Sockets::{
         StreamSocket, StreamSocketListener, StreamSocketListenerConnectionReceivedEventArgs,
     },
-    Storage::Streams::{DataReader, InputStreamOptions},
+    Storage::Streams::{DataReader, DataWriter, InputStreamOptions},
 };
 
 use crate::{
-    app::Args,
+    app::{Args, PeerAddr, check_access},
     input::Message,
-    transport::{Transport, TransportConnection},
+    transport::{Transport, TransportConnection, pin_line},
 };
 
 pub struct BluetoothTransport {
@@ -91,19 +91,35 @@ impl Transport for BluetoothTransport {
         Ok(())
     }
 
-    async fn accept(&mut self, _args: Args) -> anyhow::Result<Self::Connection> {
+    async fn accept(&mut self, args: Args) -> anyhow::Result<Self::Connection> {
         let receiver = self
             .receiver
             .as_mut()
             .ok_or_else(|| anyhow!("Bluetooth transport is not listening. Ensure Windows Bluetooth is enabled and the application has permissions."))?;
-        info!("Waiting for a Windows Bluetooth connection");
-        let socket = receiver
-            .recv()
-            .await
-            .ok_or_else(|| anyhow!("Bluetooth listener connection channel closed unexpectedly."))?;
-        info!("Windows Bluetooth connection received");
-
-        BluetoothTransportConnection::new(socket)
+        loop {
+            info!("Waiting for a Windows Bluetooth connection");
+            let socket = receiver.recv().await.ok_or_else(|| {
+                anyhow!("Bluetooth listener connection channel closed unexpectedly.")
+            })?;
+            info!("Windows Bluetooth connection received");
+
+            // Without an address every such peer would share one identity,
+            // and with it pairing and approval answers
+            let Some(host) = remote_host(&socket) else {
+                warn!("Refused a Windows Bluetooth connection that has no remote address");
+                let _ = socket.Close();
+                continue;
+            };
+            let connection = BluetoothTransportConnection::new(socket, host)?;
+            let address = connection.peer_address().unwrap_or_default();
+            match check_access(&args, PeerAddr::Bluetooth(&address)) {
+                Ok(()) => return Ok(connection),
+                Err(err) => {
+                    warn!("{err}");
+                    let _ = connection.socket.Close();
+                }
+            }
+        }
     }
 }
 
@@ -119,31 +135,34 @@ impl Drop for BluetoothTransport {
     }
 }
 
+/// Display name of the remote device, e.g. `(00:11:22:33:44:55)`
+fn remote_host(socket: &StreamSocket) -> Option<String> {
+    let host = socket.Information().ok()?.RemoteAddress().ok()?;
+    Some(host.DisplayName().ok()?.to_string()).filter(|name| !name.is_empty())
+}
+
 pub struct BluetoothTransportConnection {
     socket: StreamSocket,
     reader: DataReader,
+    host: String,
 }
 
 impl BluetoothTransportConnection {
-    fn new(socket: StreamSocket) -> anyhow::Result<Self> {
+    fn new(socket: StreamSocket, host: String) -> anyhow::Result<Self> {
         let reader = DataReader::CreateDataReader(&socket.InputStream()?)?;
         reader.SetInputStreamOptions(InputStreamOptions::Partial)?;
 
-        Ok(Self { socket, reader })
+        Ok(Self {
+            socket,
+            reader,
+            host,
+        })
     }
 }
 
-impl TransportConnection for BluetoothTransportConnection {
-    fn peer_name(&self) -> String {
-        self.socket
-            .Information()
-            .ok()
-            .and_then(|info| info.RemoteAddress().ok())
-            .map(|host| format!("droidpad-{}", host.DisplayName().unwrap_or_default()))
-            .unwrap_or_else(|| String::from("droidpad-bluetooth"))
-    }
-
-    async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
+impl BluetoothTransportConnection {
+    /// Reads the next line, or None once the peer closes
+    async fn recv_line(&mut self) -> anyhow::Result<Option<String>> {
         let mut line = Vec::new();
 
         loop {
@@ -167,9 +186,40 @@ impl TransportConnection for BluetoothTransportConnection {
         if line.is_empty() {
             return Ok(None);
         }
+        Ok(Some(line))
+    }
+}
+
+impl TransportConnection for BluetoothTransportConnection {
+    fn peer_name(&self) -> String {
+        format!("droidpad-{}", self.host)
+    }
+
+    fn peer_id(&self) -> String {
+        format!("bt:{}", self.host)
+    }
+
+    fn peer_address(&self) -> Option<String> {
+        Some(self.host.trim_matches(|c| c == '(' || c == ')').to_string())
+    }
+
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(self.recv_line().await?.as_deref().and_then(pin_line))
+    }
+
+    async fn send_token(&mut self, token: &str) -> anyhow::Result<bool> {
+        let writer = DataWriter::CreateDataWriter(&self.socket.OutputStream()?)?;
+        writer.WriteBytes(format!("token,{token}\n").as_bytes())?;
+        writer.StoreAsync()?.await?;
+        writer.DetachStream()?;
+        Ok(true)
+    }
+
+    async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
+        let Some(line) = self.recv_line().await? else {
+            return Ok(None);
+        };
 
-        Message::from_droidpad_csv(&line)
-            .context("Failed to parse Windows Bluetooth RFCOMM message")
-            .map(Some)
+        Ok(Some(Message::decode(&line)?))
     }
 }
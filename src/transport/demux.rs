@@ -0,0 +1,151 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use log::{info, warn};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::app::{Args, RateLimitAction};
+
+/// Payloads queued per peer before new ones are dropped
+const PEER_QUEUE_SIZE: usize = 64;
+/// How long everything from a rejected sender is dropped
+const REJECTED_COOLDOWN: Duration = Duration::from_secs(5);
+
+type Peers<K> = Arc<Mutex<HashMap<K, mpsc::Sender<Vec<u8>>>>>;
+/// Senders turned away, until when their payloads are dropped
+type Rejected<K> = Arc<Mutex<HashMap<K, Instant>>>;
+
+/// Warns that throttling can't hold back everything a datagram transport
+/// receives, since a throttled peer's queue fills and later payloads are
+/// dropped
+pub fn warn_if_throttled(transport: &str, args: &Args) {
+    if args.max_message_rate > 0 && args.rate_limit_action == RateLimitAction::Throttle {
+        warn!(
+            "{transport} peers over {} messages per second are throttled, but messages past the \
+             {PEER_QUEUE_SIZE} queued per peer are dropped. Consider --rate-limit-action disconnect",
+            args.max_message_rate
+        );
+    }
+}
+
+/// Splits one stream of payloads from many senders into a queue per sender, for
+/// transports without connections of their own
+pub struct Demux<K> {
+    peers: Peers<K>,
+    rejected: Rejected<K>,
+}
+
+impl<K> Default for Demux<K> {
+    fn default() -> Self {
+        Self {
+            peers: Arc::new(Mutex::new(HashMap::new())),
+            rejected: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone + Display> Demux<K> {
+    /// Hands `payload` to the queue of `key`, returning a new queue when the
+    /// sender isn't known yet or its previous queue was dropped. Payloads of
+    /// recently rejected senders are dropped
+    pub fn route(&self, key: K, payload: Vec<u8>) -> anyhow::Result<Option<PeerQueue<K>>> {
+        {
+            let mut rejected = self
+                .rejected
+                .lock()
+                .map_err(|_| anyhow!("Failed to lock rejected peers"))?;
+            let now = Instant::now();
+            rejected.retain(|_, until| now < *until);
+            if rejected.contains_key(&key) {
+                return Ok(None);
+            }
+        }
+
+        let mut peers = self
+            .peers
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock peers"))?;
+
+        let payload = match peers.get(&key) {
+            Some(sender) => match sender.try_send(payload) {
+                Ok(()) => return Ok(None),
+                Err(TrySendError::Full(_)) => {
+                    warn!("Dropping message from {key}, peer is not keeping up");
+                    return Ok(None);
+                }
+                Err(TrySendError::Closed(payload)) => payload,
+            },
+            None => payload,
+        };
+
+        let (sender, receiver) = mpsc::channel(PEER_QUEUE_SIZE);
+        // Can't fail, the channel is empty and the receiver is alive
+        let _ = sender.try_send(payload);
+        peers.insert(key.clone(), sender);
+
+        Ok(Some(PeerQueue {
+            receiver,
+            key,
+            peers: self.peers.clone(),
+            rejected: self.rejected.clone(),
+        }))
+    }
+}
+
+/// Everything one sender routed through a `Demux`
+pub struct PeerQueue<K: Eq + Hash> {
+    receiver: mpsc::Receiver<Vec<u8>>,
+    key: K,
+    peers: Peers<K>,
+    rejected: Rejected<K>,
+}
+
+impl<K: Eq + Hash + Clone + Display> PeerQueue<K> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Drops everything the sender sends for a while, so it isn't handed a
+    /// new queue on its very next payload
+    pub fn reject(&self) {
+        if let Ok(mut rejected) = self.rejected.lock() {
+            rejected.insert(self.key.clone(), Instant::now() + REJECTED_COOLDOWN);
+        }
+    }
+
+    /// Waits for the next payload, `None` once the sender was silent for
+    /// longer than `timeout`
+    pub async fn recv(&mut self, timeout: Duration) -> Option<Vec<u8>> {
+        match tokio::time::timeout(timeout, self.receiver.recv()).await {
+            Ok(payload) => payload,
+            Err(_) => {
+                info!(
+                    "No traffic from {} for {}ms, closing",
+                    self.key,
+                    timeout.as_millis()
+                );
+                None
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash> Drop for PeerQueue<K> {
+    fn drop(&mut self) {
+        self.receiver.close();
+        // A newer queue for the same sender may already have replaced this one
+        if let Ok(mut peers) = self.peers.lock()
+            && peers
+                .get(&self.key)
+                .is_some_and(|sender| sender.is_closed())
+        {
+            peers.remove(&self.key);
+        }
+    }
+}
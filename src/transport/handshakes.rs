@@ -0,0 +1,56 @@
+use std::{fmt::Display, time::Duration};
+
+use anyhow::anyhow;
+use log::warn;
+use tokio::task::JoinSet;
+
+use crate::app::Args;
+
+/// Handshakes running in their own tasks, so a client that never finishes
+/// one can't keep the accept loop from serving everyone else
+pub struct Handshakes<C> {
+    pending: JoinSet<anyhow::Result<C>>,
+}
+
+impl<C> Default for Handshakes<C> {
+    fn default() -> Self {
+        Self {
+            pending: JoinSet::new(),
+        }
+    }
+}
+
+impl<C: Send + 'static> Handshakes<C> {
+    /// Whether another handshake may start under `--max-pending-handshakes`
+    pub fn has_room(&self, args: &Args) -> bool {
+        self.pending.len() < args.max_pending_handshakes.max(1)
+    }
+
+    /// Runs `handshake` in the background, giving up after `--handshake-timeout-ms`
+    pub fn spawn<F>(&mut self, peer: impl Display, args: &Args, handshake: F)
+    where
+        F: Future<Output = anyhow::Result<C>> + Send + 'static,
+    {
+        let peer = peer.to_string();
+        let timeout = Duration::from_millis(args.handshake_timeout_ms);
+        self.pending.spawn(async move {
+            tokio::time::timeout(timeout, handshake)
+                .await
+                .map_err(|_| anyhow!("Handshake with {peer} timed out"))?
+                .map_err(|err| anyhow!("Handshake with {peer} failed: {err}"))
+        });
+    }
+
+    /// Waits for the next handshake to succeed, logging the ones that fail.
+    /// Pends forever while none are running
+    pub async fn next(&mut self) -> C {
+        loop {
+            match self.pending.join_next().await {
+                Some(Ok(Ok(connection))) => return connection,
+                Some(Ok(Err(err))) => warn!("{err}"),
+                Some(Err(err)) => warn!("Handshake task failed: {err}"),
+                None => std::future::pending().await,
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::{net::SocketAddr, sync::Mutex};
+
+use anyhow::anyhow;
+use log::{info, warn};
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+
+use crate::app::Args;
+
+/// DNS-SD service type for transports on top of TCP
+#[cfg(any(feature = "ws", feature = "tcp"))]
+pub const TCP_SERVICE: &str = "_droidpad._tcp.local.";
+/// DNS-SD service type for transports on top of UDP
+#[cfg(feature = "udp")]
+pub const UDP_SERVICE: &str = "_droidpad._udp.local.";
+
+/// Daemon every advertisement goes through, and how many are using it. It's
+/// shut down once the last one is dropped
+static DAEMON: Mutex<Option<(ServiceDaemon, usize)>> = Mutex::new(None);
+
+/// A service announced on the local network until it's dropped
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        if let Err(err) = self.daemon.unregister(&self.fullname) {
+            warn!("Failed to stop advertising {}: {err}", self.fullname);
+        }
+        release_daemon();
+    }
+}
+
+fn acquire_daemon() -> anyhow::Result<ServiceDaemon> {
+    let mut shared = DAEMON
+        .lock()
+        .map_err(|_| anyhow!("Failed to lock the mDNS daemon"))?;
+    let (daemon, users) = match shared.take() {
+        Some(shared) => shared,
+        None => (ServiceDaemon::new()?, 0),
+    };
+    *shared = Some((daemon.clone(), users + 1));
+    Ok(daemon)
+}
+
+fn release_daemon() {
+    let Ok(mut shared) = DAEMON.lock() else {
+        return;
+    };
+    if let Some((daemon, users)) = shared.take() {
+        if users > 1 {
+            *shared = Some((daemon, users - 1));
+        } else {
+            let _ = daemon.shutdown();
+        }
+    }
+}
+
+/// Announces a transport listening on `addrs` over mDNS, with its protocol
+/// and pairing requirements in TXT records. None when `--disable-mdns` is
+/// set or the announcement failed, which only gets logged
+pub fn advertise(
+    service_type: &str,
+    protocol: &str,
+    addrs: &[SocketAddr],
+    args: &Args,
+    extra: &[(&str, String)],
+) -> Option<Advertisement> {
+    if args.disable_mdns {
+        return None;
+    }
+    let port = addrs.first()?.port();
+
+    match register(service_type, protocol, addrs, port, args, extra) {
+        Ok(advertisement) => {
+            info!(
+                "Advertising {} on port {port} over mDNS",
+                advertisement.fullname
+            );
+            Some(advertisement)
+        }
+        Err(err) => {
+            warn!("Failed to advertise {protocol} over mDNS: {err}");
+            None
+        }
+    }
+}
+
+fn register(
+    service_type: &str,
+    protocol: &str,
+    addrs: &[SocketAddr],
+    port: u16,
+    args: &Args,
+    extra: &[(&str, String)],
+) -> anyhow::Result<Advertisement> {
+    let host = host_name();
+    let pairing = if args.require_pairing {
+        "required"
+    } else {
+        "none"
+    };
+    let mut properties = vec![
+        ("proto", protocol.to_string()),
+        ("host", host.clone()),
+        ("pairing", pairing.to_string()),
+        ("version", env!("CARGO_PKG_VERSION").to_string()),
+    ];
+    properties.extend(extra.iter().cloned());
+
+    let instance = format!("{host} ({protocol})");
+    let host_name = format!("{host}.local.");
+    // Wildcard binds are reachable on every interface, which the daemon
+    // keeps track of by itself
+    let ips = addrs
+        .iter()
+        .map(SocketAddr::ip)
+        .filter(|ip| !ip.is_unspecified())
+        .collect::<Vec<_>>();
+    let service = if ips.len() < addrs.len() {
+        ServiceInfo::new(
+            service_type,
+            &instance,
+            &host_name,
+            (),
+            port,
+            properties.as_slice(),
+        )?
+        .enable_addr_auto()
+    } else {
+        ServiceInfo::new(
+            service_type,
+            &instance,
+            &host_name,
+            ips.as_slice(),
+            port,
+            properties.as_slice(),
+        )?
+    };
+
+    let daemon = acquire_daemon()?;
+    let fullname = service.get_fullname().to_string();
+    if let Err(err) = daemon.register(service) {
+        release_daemon();
+        return Err(err.into());
+    }
+    Ok(Advertisement { daemon, fullname })
+}
+
+/// This machine's name, for the mDNS host and instance names
+fn host_name() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from("droidpad-gamepad"))
+}
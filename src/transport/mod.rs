@@ -1,8 +1,53 @@
 #[cfg(feature = "bluetooth")]
 pub mod bluetooth;
+#[cfg(any(feature = "udp", feature = "mqtt"))]
+mod demux;
+#[cfg(any(feature = "ws", all(feature = "bluetooth", target_os = "linux")))]
+mod handshakes;
+#[cfg(all(
+    feature = "mdns",
+    any(feature = "ws", feature = "tcp", feature = "udp")
+))]
+mod mdns;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(any(feature = "ws", feature = "tcp", feature = "udp"))]
+mod net;
+#[cfg(feature = "serial")]
+pub mod serial;
+#[cfg(feature = "tcp")]
+pub mod tcp;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(feature = "udp")]
+pub mod udp;
+#[cfg(all(unix, feature = "unix"))]
+pub mod unix;
 #[cfg(feature = "ws")]
 pub mod ws;
 
+#[cfg(any(
+    feature = "tcp",
+    all(unix, feature = "unix"),
+    feature = "serial",
+    all(feature = "bluetooth", target_os = "linux")
+))]
+use anyhow::anyhow;
+#[cfg(any(
+    feature = "tcp",
+    all(unix, feature = "unix"),
+    feature = "serial",
+    all(feature = "bluetooth", target_os = "linux")
+))]
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+#[cfg(any(
+    feature = "tcp",
+    all(unix, feature = "unix"),
+    feature = "serial",
+    all(feature = "bluetooth", target_os = "linux")
+))]
+use crate::input::InvalidMessage;
 use crate::{app::Args, input::Message};
 
 pub trait Transport {
@@ -15,5 +60,186 @@ pub trait Transport {
 pub trait TransportConnection {
     fn peer_name(&self) -> String;
 
+    /// Stable identity of the remote device the client can't choose, used to
+    /// remember approvals and hand back parked controllers
+    fn peer_id(&self) -> String;
+
+    /// Identity the player slot is remembered under. Unlike `peer_id` the
+    /// client may pick it, so it only decides which slot a peer gets
+    fn slot_hint(&self) -> String {
+        self.peer_id()
+    }
+
+    /// Address access list entries are matched against, None when the
+    /// transport has no such address
+    fn peer_address(&self) -> Option<String>;
+
+    /// Reads the pairing PIN, or the token handed out at pairing, the peer
+    /// presents before sending any input
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>>;
+
+    /// Hands the peer the token it presents instead of the PIN from now on,
+    /// as a `token,<TOKEN>` line. False when the transport can't answer, so
+    /// the peer isn't remembered
+    async fn send_token(&mut self, _token: &str) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
     async fn recv_message(&mut self) -> anyhow::Result<Option<Message>>;
+
+    /// Called when the peer was turned away before it got a controller.
+    /// Transports that open a connection for every unknown sender ignore it
+    /// for a while, instead of treating its next message as a new attempt
+    fn reject(&mut self) {}
+}
+
+/// Takes the PIN out of a `pin,<PIN>` line, which is how clients without a
+/// handshake present it as their first message
+#[cfg(any(
+    feature = "bluetooth",
+    feature = "tcp",
+    feature = "udp",
+    feature = "mqtt",
+    all(unix, feature = "unix"),
+    feature = "serial"
+))]
+pub fn pin_line(line: &str) -> Option<String> {
+    let (key, pin) = line.split_once(',')?;
+    key.trim()
+        .eq_ignore_ascii_case("pin")
+        .then(|| pin.trim().to_string())
+}
+
+/// Longest line stream transports read, far above any real message
+#[cfg(any(
+    feature = "tcp",
+    all(unix, feature = "unix"),
+    feature = "serial",
+    all(feature = "bluetooth", target_os = "linux")
+))]
+const MAX_LINE: usize = 16 * 1024;
+
+/// Reads the next non-empty line, or None at the end of the stream. Lines
+/// over `MAX_LINE` bytes or not valid UTF-8 are skipped and come back as
+/// [`InvalidMessage`], so `--parse-error-policy` decides what happens
+#[cfg(any(
+    feature = "tcp",
+    all(unix, feature = "unix"),
+    feature = "serial",
+    all(feature = "bluetooth", target_os = "linux")
+))]
+pub async fn read_line<R>(reader: &mut R) -> anyhow::Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    loop {
+        let mut line = Vec::new();
+        let bytes_read = (&mut *reader)
+            .take(MAX_LINE as u64 + 1)
+            .read_until(b'\n', &mut line)
+            .await?;
+        if bytes_read == 0 {
+            return Ok(None);
+        }
+        if line.len() > MAX_LINE && line.last() != Some(&b'\n') {
+            skip_line(reader).await?;
+            return Err(InvalidMessage(anyhow!("Line is longer than {MAX_LINE} bytes")).into());
+        }
+
+        let line = String::from_utf8(line)
+            .map_err(|_| InvalidMessage(anyhow!("Line is not valid UTF-8")))?;
+        let line = line.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        if !line.is_empty() {
+            return Ok(Some(line.to_string()));
+        }
+    }
+}
+
+/// Drops everything up to and including the next newline
+#[cfg(any(
+    feature = "tcp",
+    all(unix, feature = "unix"),
+    feature = "serial",
+    all(feature = "bluetooth", target_os = "linux")
+))]
+async fn skip_line<R>(reader: &mut R) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+{
+    loop {
+        let buf = reader.fill_buf().await?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        match buf.iter().position(|byte| *byte == b'\n') {
+            Some(end) => {
+                reader.consume(end + 1);
+                return Ok(());
+            }
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+/// Most addresses put in a QR code, so it stays small enough to scan
+#[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+const MAX_CONNECTION_URLS: usize = 4;
+
+/// URLs phones on the network can reach `kind` at, best first, with the
+/// pairing PIN attached when pairing is required. Empty for transports that
+/// don't listen on an address of their own
+#[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+pub fn connection_urls(kind: crate::app::TransportKind, args: &Args) -> Vec<String> {
+    use crate::app::{TransportKind, pairing_pin};
+
+    let (scheme, port) = match kind {
+        #[cfg(feature = "ws")]
+        TransportKind::Ws => {
+            #[cfg(feature = "tls")]
+            let scheme = if args.tls { "wss" } else { "ws" };
+            #[cfg(not(feature = "tls"))]
+            let scheme = "ws";
+            (scheme, args.port)
+        }
+        #[cfg(feature = "tcp")]
+        TransportKind::Tcp => ("tcp", args.tcp_port),
+        #[cfg(feature = "udp")]
+        TransportKind::Udp => ("udp", args.udp_port),
+        #[allow(unreachable_patterns)]
+        _ => return Vec::new(),
+    };
+    let Ok(addrs) = net::resolve(&args.bind, port) else {
+        return Vec::new();
+    };
+    let query = pairing_pin(args)
+        .map(|pin| {
+            // Query values are percent-decoded, so anything but unreserved
+            // characters has to be escaped
+            let pin = pin
+                .bytes()
+                .map(|byte| match byte {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                        char::from(byte).to_string()
+                    }
+                    _ => format!("%{byte:02X}"),
+                })
+                .collect::<String>();
+            format!("/?pin={pin}")
+        })
+        .unwrap_or_default();
+
+    net::reachable(&addrs)
+        .into_iter()
+        .map(|(_, addr)| addr)
+        // Neither is any use to another device
+        .filter(|addr| match addr.ip() {
+            std::net::IpAddr::V4(ip) => !ip.is_loopback() && !ip.is_link_local(),
+            std::net::IpAddr::V6(ip) => !ip.is_loopback() && !ip.is_unicast_link_local(),
+        })
+        .take(MAX_CONNECTION_URLS)
+        .map(|addr| format!("{scheme}://{addr}{query}"))
+        .collect()
 }
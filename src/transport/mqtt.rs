@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use log::{info, warn};
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+
+use super::{
+    Transport, TransportConnection,
+    demux::{self, Demux, PeerQueue},
+    pin_line,
+};
+use crate::{app::Args, input::Message};
+
+/// How long to wait before polling the broker again after a connection error
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub struct MqttTransport {
+    client: Option<(AsyncClient, EventLoop)>,
+    devices: Demux<String>,
+}
+
+impl MqttTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Transport for MqttTransport {
+    type Connection = MqttTransportConnection;
+
+    async fn listen(&mut self, args: Args) -> anyhow::Result<()> {
+        if self.client.is_some() {
+            return Ok(());
+        }
+
+        let mut options = MqttOptions::new(
+            format!("droidpad-gamepad-{}", std::process::id()),
+            &args.mqtt_host,
+            args.mqtt_port,
+        );
+        options.set_keep_alive(Duration::from_secs(10));
+        if let Some(username) = &args.mqtt_username {
+            options.set_credentials(username, args.mqtt_password.clone().unwrap_or_default());
+        }
+
+        info!(
+            "Connecting to MQTT broker {}:{}, subscribing to {}",
+            args.mqtt_host, args.mqtt_port, args.mqtt_topic
+        );
+        self.client = Some(AsyncClient::new(options, 16));
+        demux::warn_if_throttled("MQTT", &args);
+
+        Ok(())
+    }
+
+    /// Drives the broker connection until a message arrives from a device
+    /// without a controller yet
+    async fn accept(&mut self, args: Args) -> anyhow::Result<Self::Connection> {
+        let (client, event_loop) = self
+            .client
+            .as_mut()
+            .ok_or_else(|| anyhow!("MQTT transport is not listening"))?;
+
+        loop {
+            let event = match event_loop.poll().await {
+                Ok(event) => event,
+                Err(err) => {
+                    warn!("MQTT broker connection failed: {err}");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
+
+            match event {
+                // Subscriptions don't survive a reconnect with a clean session
+                Event::Incoming(Packet::ConnAck(_)) => {
+                    info!("Connected to MQTT broker");
+                    client
+                        .subscribe(&args.mqtt_topic, QoS::AtMostOnce)
+                        .await
+                        .context("Failed to subscribe to MQTT topic")?;
+                }
+                Event::Incoming(Packet::Publish(publish)) => {
+                    let device = device_id(&args.mqtt_topic, &publish.topic);
+                    if let Some(queue) = self.devices.route(device, publish.payload.to_vec())? {
+                        return Ok(MqttTransportConnection {
+                            queue,
+                            timeout: Duration::from_millis(args.mqtt_timeout_ms),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Picks the part of `topic` that identifies the publishing device: the level
+/// matched by the pattern's first `+`, or the levels matched by a trailing `#`,
+/// or the whole topic when the pattern has no wildcard
+fn device_id(pattern: &str, topic: &str) -> String {
+    let levels = topic.split('/').collect::<Vec<_>>();
+    for (index, level) in pattern.split('/').enumerate() {
+        match level {
+            "+" => {
+                if let Some(device) = levels.get(index) {
+                    return device.to_string();
+                }
+            }
+            "#" if index < levels.len() => return levels[index..].join("/"),
+            _ => {}
+        }
+    }
+    topic.to_string()
+}
+
+/// Every message published by one device, ended after a period without any
+pub struct MqttTransportConnection {
+    queue: PeerQueue<String>,
+    timeout: Duration,
+}
+
+impl MqttTransportConnection {
+    /// Waits for the next non-empty message, or None once the phone went quiet
+    async fn recv_text(&mut self) -> Option<String> {
+        loop {
+            let payload = self.queue.recv(self.timeout).await?;
+            let text = String::from_utf8_lossy(&payload);
+            let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+            if !text.is_empty() {
+                return Some(text.to_string());
+            }
+        }
+    }
+}
+
+impl TransportConnection for MqttTransportConnection {
+    fn peer_name(&self) -> String {
+        format!("droidpad-{}", self.queue.key())
+    }
+
+    fn peer_id(&self) -> String {
+        format!("mqtt:{}", self.queue.key())
+    }
+
+    fn peer_address(&self) -> Option<String> {
+        None
+    }
+
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(self.recv_text().await.as_deref().and_then(pin_line))
+    }
+
+    async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
+        let Some(text) = self.recv_text().await else {
+            return Ok(None);
+        };
+
+        Ok(Some(Message::decode(&text)?))
+    }
+
+    fn reject(&mut self) {
+        self.queue.reject();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_the_first_single_level_wildcard() {
+        assert_eq!(device_id("droidpad/+", "droidpad/phone1"), "phone1");
+        assert_eq!(device_id("+/input", "phone1/input"), "phone1");
+        assert_eq!(device_id("droidpad/+/+/input", "droidpad/a/b/input"), "a");
+        assert_eq!(device_id("droidpad/+", "droidpad/"), "");
+    }
+
+    #[test]
+    fn takes_every_level_of_a_trailing_wildcard() {
+        assert_eq!(device_id("droidpad/#", "droidpad/phone1"), "phone1");
+        assert_eq!(
+            device_id("droidpad/#", "droidpad/home/phone1"),
+            "home/phone1"
+        );
+        assert_eq!(device_id("#", "a/b"), "a/b");
+    }
+
+    #[test]
+    fn falls_back_to_the_whole_topic() {
+        assert_eq!(
+            device_id("droidpad/phone1", "droidpad/phone1"),
+            "droidpad/phone1"
+        );
+        // `#` also matches its parent level, which names no device
+        assert_eq!(device_id("droidpad/#", "droidpad"), "droidpad");
+        assert_eq!(device_id("droidpad/+/input", "droidpad"), "droidpad");
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use anyhow::{Context, anyhow};
+use futures_util::future::select_all;
+use log::info;
+#[cfg(any(feature = "ws", feature = "tcp"))]
+use log::warn;
+use socket2::{Domain, Protocol, Socket, Type};
+#[cfg(feature = "udp")]
+use tokio::net::UdpSocket;
+#[cfg(any(feature = "ws", feature = "tcp"))]
+use tokio::net::{TcpListener, TcpStream};
+
+#[cfg(any(feature = "ws", feature = "tcp"))]
+use crate::app::{Args, PeerAddr, check_access};
+
+/// Turns `--bind` entries into addresses on `port`. An entry is an IPv4 or
+/// IPv6 address, optionally in brackets, or the name of a network interface,
+/// which stands for every address it has. IPv6 link-local addresses are left
+/// out of interfaces since they can't be bound without a scope id
+pub fn resolve(bind: &[String], port: u16) -> anyhow::Result<Vec<SocketAddr>> {
+    let mut addrs = Vec::new();
+    let mut interfaces = None;
+
+    for entry in bind {
+        let entry = entry.trim();
+        let unbracketed = entry.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = unbracketed.parse::<IpAddr>() {
+            addrs.push(SocketAddr::new(ip, port));
+            continue;
+        }
+
+        let interfaces = match &mut interfaces {
+            Some(interfaces) => interfaces,
+            None => interfaces.insert(
+                local_ip_address::list_afinet_netifas()
+                    .context("Failed to list network interfaces")?,
+            ),
+        };
+        let before = addrs.len();
+        addrs.extend(
+            interfaces
+                .iter()
+                .filter(|(name, ip)| name == entry && !is_link_local_v6(*ip))
+                .map(|(_, ip)| SocketAddr::new(*ip, port)),
+        );
+        if addrs.len() == before {
+            return Err(anyhow!(
+                "`{entry}` is neither an IP address nor a network interface with an address"
+            ));
+        }
+    }
+
+    addrs.sort();
+    addrs.dedup();
+
+    // A wildcard already covers the specific addresses of its family, and
+    // binding both would collide. `[::]` covers IPv4 too as it's dual-stack
+    // whenever `0.0.0.0` isn't bound
+    let v4_wildcard = addrs.iter().any(|addr| addr.ip() == Ipv4Addr::UNSPECIFIED);
+    let v6_wildcard = addrs.iter().any(|addr| addr.ip() == Ipv6Addr::UNSPECIFIED);
+    addrs.retain(|addr| match addr.ip() {
+        ip if ip.is_unspecified() => true,
+        IpAddr::V4(_) => !v4_wildcard && !v6_wildcard,
+        IpAddr::V6(_) => !v6_wildcard,
+    });
+
+    if addrs.is_empty() {
+        return Err(anyhow!("No address to bind to"));
+    }
+    Ok(addrs)
+}
+
+fn is_link_local_v6(ip: IpAddr) -> bool {
+    matches!(ip, IpAddr::V6(ip) if ip.is_unicast_link_local())
+}
+
+/// Creates a socket for `addr`. An IPv6 wildcard socket also accepts IPv4
+/// when `dual_stack` is set, so `[::]` alone covers both on every platform
+fn socket(addr: SocketAddr, ty: Type, protocol: Protocol, dual_stack: bool) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(addr), ty, Some(protocol))?;
+    if addr.ip() == IpAddr::V6(Ipv6Addr::UNSPECIFIED) {
+        socket.set_only_v6(!dual_stack)?;
+    }
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+/// `[::]` only takes IPv4 too when no IPv4 address is bound separately,
+/// otherwise the two would collide
+fn is_dual_stack(addrs: &[SocketAddr]) -> bool {
+    !addrs.iter().any(SocketAddr::is_ipv4)
+}
+
+#[cfg(any(feature = "ws", feature = "tcp"))]
+pub fn bind_tcp(addrs: &[SocketAddr]) -> anyhow::Result<Vec<TcpListener>> {
+    let dual_stack = is_dual_stack(addrs);
+    let bind = |addr: SocketAddr| -> io::Result<TcpListener> {
+        let socket = socket(addr, Type::STREAM, Protocol::TCP, dual_stack)?;
+        #[cfg(not(windows))]
+        socket.set_reuse_address(true)?;
+        socket.bind(&addr.into())?;
+        socket.listen(1024)?;
+        TcpListener::from_std(socket.into())
+    };
+
+    addrs
+        .iter()
+        .map(|addr| bind(*addr).map_err(|err| anyhow!("Failed to listen on {addr}: {err}")))
+        .collect()
+}
+
+#[cfg(feature = "udp")]
+pub fn bind_udp(addrs: &[SocketAddr]) -> anyhow::Result<Vec<UdpSocket>> {
+    let dual_stack = is_dual_stack(addrs);
+    let bind = |addr: SocketAddr| -> io::Result<UdpSocket> {
+        let socket = socket(addr, Type::DGRAM, Protocol::UDP, dual_stack)?;
+        socket.bind(&addr.into())?;
+        UdpSocket::from_std(socket.into())
+    };
+
+    addrs
+        .iter()
+        .map(|addr| bind(*addr).map_err(|err| anyhow!("Failed to bind {addr}: {err}")))
+        .collect()
+}
+
+/// Accepts the next connection on whichever listener gets one first
+#[cfg(any(feature = "ws", feature = "tcp"))]
+async fn accept_any(listeners: &[TcpListener]) -> io::Result<(TcpStream, SocketAddr)> {
+    let (accepted, _, _) =
+        select_all(listeners.iter().map(|listener| Box::pin(listener.accept()))).await;
+    accepted
+}
+
+/// Accepts the next connection that passes the access lists, logging and
+/// closing the others
+#[cfg(any(feature = "ws", feature = "tcp"))]
+pub async fn accept_allowed(
+    listeners: &[TcpListener],
+    args: &Args,
+) -> io::Result<(TcpStream, SocketAddr)> {
+    loop {
+        let (stream, peer_addr) = accept_any(listeners).await?;
+        match check_access(args, PeerAddr::Ip(peer_addr.ip())) {
+            Ok(()) => return Ok((stream, peer_addr)),
+            Err(err) => warn!("{err}"),
+        }
+    }
+}
+
+/// Receives the next datagram on whichever socket gets one first
+#[cfg(feature = "udp")]
+pub async fn recv_any(sockets: &[UdpSocket], buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+    loop {
+        let (readable, index, _) =
+            select_all(sockets.iter().map(|socket| Box::pin(socket.readable()))).await;
+        readable?;
+
+        match sockets[index].try_recv_from(buf) {
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+            received => return received,
+        }
+    }
+}
+
+/// Logs every address a phone can use to reach the listeners
+pub fn log_reachable(scheme: &str, addrs: &[SocketAddr]) {
+    for (interface, addr) in reachable(addrs) {
+        match interface {
+            Some(interface) => info!("Reachable at {scheme}://{addr} ({interface})"),
+            None => info!("Reachable at {scheme}://{addr}"),
+        }
+    }
+}
+
+/// Every address a phone can use to reach the listeners, with the interface
+/// it's on, IPv4 first and loopback last. Wildcard binds are expanded to the
+/// addresses of all interfaces
+pub fn reachable(addrs: &[SocketAddr]) -> Vec<(Option<String>, SocketAddr)> {
+    let interfaces = local_ip_address::list_afinet_netifas().unwrap_or_default();
+
+    let mut reachable = Vec::new();
+    for addr in addrs {
+        let ips = match addr.ip() {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED) => interfaces
+                .iter()
+                .map(|(_, ip)| *ip)
+                .filter(IpAddr::is_ipv4)
+                .collect(),
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED) => interfaces
+                .iter()
+                .map(|(_, ip)| *ip)
+                .filter(|ip| ip.is_ipv6() || is_dual_stack(addrs))
+                .collect(),
+            ip => vec![ip],
+        };
+        reachable.extend(ips.into_iter().map(|ip| {
+            (
+                interface_of(&interfaces, ip).map(String::from),
+                SocketAddr::new(ip, addr.port()),
+            )
+        }));
+    }
+    reachable.sort_by_key(|(_, addr)| (addr.ip().is_loopback(), addr.is_ipv6(), *addr));
+    reachable.dedup_by_key(|(_, addr)| *addr);
+    reachable
+}
+
+fn interface_of(interfaces: &[(String, IpAddr)], ip: IpAddr) -> Option<&str> {
+    interfaces
+        .iter()
+        .find(|(_, interface_ip)| *interface_ip == ip)
+        .map(|(name, _)| name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(bind: &[&str]) -> anyhow::Result<Vec<String>> {
+        let bind = bind
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect::<Vec<_>>();
+        Ok(resolve(&bind, 1715)?
+            .iter()
+            .map(SocketAddr::to_string)
+            .collect())
+    }
+
+    #[test]
+    fn resolves_addresses() {
+        assert_eq!(
+            resolved(&["192.168.1.20", " [fe80::1] ", "::1"]).unwrap(),
+            ["192.168.1.20:1715", "[::1]:1715", "[fe80::1]:1715"]
+        );
+        assert_eq!(
+            resolved(&["10.0.0.1", "10.0.0.1"]).unwrap(),
+            ["10.0.0.1:1715"]
+        );
+    }
+
+    #[test]
+    fn wildcards_cover_their_family() {
+        assert_eq!(
+            resolved(&["0.0.0.0", "10.0.0.1", "::1"]).unwrap(),
+            ["0.0.0.0:1715", "[::1]:1715"]
+        );
+        assert_eq!(resolved(&["::", "10.0.0.1", "::1"]).unwrap(), ["[::]:1715"]);
+        assert_eq!(
+            resolved(&["0.0.0.0", "::"]).unwrap(),
+            ["0.0.0.0:1715", "[::]:1715"]
+        );
+    }
+
+    #[test]
+    fn resolves_interfaces() {
+        let interfaces = local_ip_address::list_afinet_netifas().unwrap();
+        for (name, ip) in &interfaces {
+            let Ok(addrs) = resolve(std::slice::from_ref(name), 0) else {
+                // Only has link-local IPv6 addresses
+                assert!(is_link_local_v6(*ip));
+                continue;
+            };
+            assert!(addrs.contains(&SocketAddr::new(*ip, 0)) || is_link_local_v6(*ip));
+            for addr in addrs {
+                socket(addr, Type::DGRAM, Protocol::UDP, false)
+                    .and_then(|socket| socket.bind(&addr.into()))
+                    .unwrap_or_else(|err| panic!("Failed to bind {addr} of {name}: {err}"));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_entries() {
+        assert!(resolved(&["no-such-interface0"]).is_err());
+        assert!(resolved(&["192.168.1.300"]).is_err());
+        assert!(resolved(&[]).is_err());
+    }
+}
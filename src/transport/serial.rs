@@ -0,0 +1,144 @@
+use std::{io, sync::Arc, time::Duration};
+
+use anyhow::anyhow;
+use log::{debug, info, warn};
+use tokio::{
+    io::{AsyncWriteExt, BufReader},
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+use super::{Transport, TransportConnection, pin_line, read_line};
+use crate::{app::Args, input::Message};
+
+/// Serves one character device, reopening it whenever it goes away
+pub struct SerialTransport {
+    /// Held by the open connection, so the device is only opened once
+    open: Arc<Semaphore>,
+    /// Whether the device was opened before, so reopening waits first
+    reopening: bool,
+}
+
+impl SerialTransport {
+    pub fn new() -> Self {
+        Self {
+            open: Arc::new(Semaphore::new(1)),
+            reopening: false,
+        }
+    }
+}
+
+impl Transport for SerialTransport {
+    type Connection = SerialTransportConnection;
+
+    async fn listen(&mut self, args: Args) -> anyhow::Result<()> {
+        if args.serial_device.trim().is_empty() {
+            return Err(anyhow!("No serial device set, pass --serial-device"));
+        }
+        info!(
+            "Reading {} at {} baud",
+            args.serial_device, args.serial_baud_rate
+        );
+        Ok(())
+    }
+
+    /// Waits for the previous connection to close, then opens the device
+    /// again, retrying every `--serial-reopen-ms` while it's missing
+    async fn accept(&mut self, args: Args) -> anyhow::Result<Self::Connection> {
+        let permit = self.open.clone().acquire_owned().await?;
+        let device = args.serial_device.trim().to_string();
+        let retry = Duration::from_millis(args.serial_reopen_ms.max(1));
+        // A device that ends every connection right away would otherwise be
+        // reopened in a busy loop
+        if self.reopening {
+            tokio::time::sleep(retry).await;
+        }
+
+        let mut failed = false;
+        loop {
+            match tokio_serial::new(&device, args.serial_baud_rate).open_native_async() {
+                Ok(stream) => {
+                    info!("Opened serial device {device}");
+                    self.reopening = true;
+                    return Ok(SerialTransportConnection::new(stream, device, permit));
+                }
+                // Logged once until it works again, it may stay unplugged
+                // for a long time
+                Err(err) if failed => debug!("Failed to open {device}: {err}"),
+                Err(err) => {
+                    warn!("Failed to open {device}, retrying until it shows up: {err}");
+                    failed = true;
+                }
+            }
+            tokio::time::sleep(retry).await;
+        }
+    }
+}
+
+pub struct SerialTransportConnection {
+    reader: BufReader<SerialStream>,
+    device: String,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl SerialTransportConnection {
+    fn new(stream: SerialStream, device: String, permit: OwnedSemaphorePermit) -> Self {
+        Self {
+            reader: BufReader::new(stream),
+            device,
+            _permit: permit,
+        }
+    }
+
+    /// Reads the next non-empty line, or None once the device goes away. Line
+    /// noise, e.g. at the wrong baud rate, ends up as an invalid message
+    /// rather than closing the connection
+    async fn recv_line(&mut self) -> anyhow::Result<Option<String>> {
+        match read_line(&mut self.reader).await {
+            // Unplugged devices and closed ptys fail reads instead of
+            // reporting the end of the stream
+            Err(err) if err.is::<io::Error>() => {
+                info!("Serial device {} went away: {err}", self.device);
+                Ok(None)
+            }
+            line => line,
+        }
+    }
+}
+
+impl TransportConnection for SerialTransportConnection {
+    fn peer_name(&self) -> String {
+        let name = self.device.rsplit(['/', '\\']).next().unwrap_or_default();
+        format!("droidpad-{name}")
+    }
+
+    fn peer_id(&self) -> String {
+        format!("serial:{}", self.device)
+    }
+
+    /// Access lists don't apply to a local device
+    fn peer_address(&self) -> Option<String> {
+        None
+    }
+
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(self.recv_line().await?.as_deref().and_then(pin_line))
+    }
+
+    async fn send_token(&mut self, token: &str) -> anyhow::Result<bool> {
+        let stream = self.reader.get_mut();
+        stream
+            .write_all(format!("token,{token}\n").as_bytes())
+            .await?;
+        stream.flush().await?;
+        Ok(true)
+    }
+
+    async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
+        let Some(line) = self.recv_line().await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Message::decode(&line)?))
+    }
+}
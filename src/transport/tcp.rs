@@ -0,0 +1,107 @@
+use std::net::SocketAddr;
+
+use anyhow::anyhow;
+use tokio::{
+    io::{AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+#[cfg(feature = "mdns")]
+use super::mdns::{self, Advertisement};
+use super::{Transport, TransportConnection, net, pin_line, read_line};
+use crate::{app::Args, input::Message};
+
+#[derive(Default)]
+pub struct TcpTransport {
+    listeners: Vec<TcpListener>,
+    #[cfg(feature = "mdns")]
+    advertisement: Option<Advertisement>,
+}
+
+impl TcpTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Transport for TcpTransport {
+    type Connection = TcpTransportConnection;
+
+    async fn listen(&mut self, args: Args) -> anyhow::Result<()> {
+        if !self.listeners.is_empty() {
+            return Ok(());
+        }
+
+        let addrs = net::resolve(&args.bind, args.tcp_port)?;
+        self.listeners = net::bind_tcp(&addrs)?;
+        net::log_reachable("tcp", &addrs);
+        #[cfg(feature = "mdns")]
+        {
+            self.advertisement = mdns::advertise(mdns::TCP_SERVICE, "tcp", &addrs, &args, &[]);
+        }
+
+        Ok(())
+    }
+
+    async fn accept(&mut self, args: Args) -> anyhow::Result<Self::Connection> {
+        if self.listeners.is_empty() {
+            return Err(anyhow!("TCP transport is not listening"));
+        }
+        let (stream, peer_addr) = net::accept_allowed(&self.listeners, &args).await?;
+        stream.set_nodelay(true)?;
+
+        Ok(TcpTransportConnection::new(stream, peer_addr))
+    }
+}
+
+pub struct TcpTransportConnection {
+    reader: BufReader<TcpStream>,
+    peer_addr: SocketAddr,
+}
+
+impl TcpTransportConnection {
+    fn new(stream: TcpStream, peer_addr: SocketAddr) -> Self {
+        Self {
+            reader: BufReader::new(stream),
+            peer_addr,
+        }
+    }
+}
+
+impl TransportConnection for TcpTransportConnection {
+    fn peer_name(&self) -> String {
+        format!("droidpad-{}", self.peer_addr.ip())
+    }
+
+    fn peer_id(&self) -> String {
+        format!("ip:{}", self.peer_addr.ip())
+    }
+
+    fn peer_address(&self) -> Option<String> {
+        Some(self.peer_addr.ip().to_canonical().to_string())
+    }
+
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(read_line(&mut self.reader)
+            .await?
+            .as_deref()
+            .and_then(pin_line))
+    }
+
+    async fn send_token(&mut self, token: &str) -> anyhow::Result<bool> {
+        let stream = self.reader.get_mut();
+        stream
+            .write_all(format!("token,{token}\n").as_bytes())
+            .await?;
+        stream.flush().await?;
+        Ok(true)
+    }
+
+    async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
+        let Some(line) = read_line(&mut self.reader).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Message::decode(&line)?))
+    }
+}
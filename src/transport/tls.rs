@@ -0,0 +1,113 @@
+use std::{fs, path::Path, sync::Arc};
+
+use anyhow::{Context, anyhow};
+use log::info;
+use sha2::{Digest, Sha256};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        ServerConfig,
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    },
+};
+
+use crate::app::{Args, SettingsManager};
+
+/// The certificate a TLS listener presents, ready to accept connections
+pub struct TlsIdentity {
+    pub acceptor: TlsAcceptor,
+    /// SHA-256 of the certificate, for checking it on the phone
+    pub fingerprint: String,
+}
+
+impl TlsIdentity {
+    /// Uses the certificate and key from `--tls-cert` and `--tls-key`, or a
+    /// self-signed certificate kept in the config dir when neither is given
+    pub fn load(args: &Args) -> anyhow::Result<Self> {
+        let (cert_path, key_path) = match (&args.tls_cert, &args.tls_key) {
+            (Some(cert), Some(key)) => (cert.clone(), key.clone()),
+            (None, None) => {
+                let dir = SettingsManager::config_dir().join("tls");
+                let paths = (dir.join("cert.pem"), dir.join("key.pem"));
+                if !paths.0.exists() || !paths.1.exists() {
+                    generate_self_signed(&paths.0, &paths.1)?;
+                }
+                paths
+            }
+            _ => return Err(anyhow!("--tls-cert and --tls-key must be given together")),
+        };
+
+        let certs = CertificateDer::pem_file_iter(&cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .with_context(|| format!("Failed to read TLS certificate {cert_path:?}"))?;
+        let key = PrivateKeyDer::from_pem_file(&key_path)
+            .with_context(|| format!("Failed to read TLS key {key_path:?}"))?;
+        let fingerprint = fingerprint(
+            certs
+                .first()
+                .ok_or_else(|| anyhow!("No certificate found in {cert_path:?}"))?,
+        );
+
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("Invalid TLS certificate or key")?;
+
+        info!("TLS certificate {cert_path:?}, SHA-256 fingerprint {fingerprint}");
+        Ok(Self {
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+            fingerprint,
+        })
+    }
+}
+
+/// Creates a certificate valid for localhost and every local address
+fn generate_self_signed(cert_path: &Path, key_path: &Path) -> anyhow::Result<()> {
+    let mut names = vec![String::from("localhost")];
+    if let Ok(interfaces) = local_ip_address::list_afinet_netifas() {
+        names.extend(interfaces.into_iter().map(|(_, ip)| ip.to_string()));
+    }
+
+    let certified = rcgen::generate_simple_self_signed(names)
+        .context("Failed to generate self-signed TLS certificate")?;
+    if let Some(parent) = cert_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(cert_path, certified.cert.pem())?;
+    write_private(key_path, &certified.signing_key.serialize_pem())?;
+
+    info!("Generated self-signed TLS certificate at {cert_path:?}");
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, content: &str) -> anyhow::Result<()> {
+    use std::{io::Write, os::unix::fs::OpenOptionsExt};
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(content.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, content: &str) -> anyhow::Result<()> {
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Formats the SHA-256 of a certificate as colon separated hex, the way
+/// browsers and Android show it
+fn fingerprint(cert: &CertificateDer) -> String {
+    Sha256::digest(cert.as_ref())
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
@@ -0,0 +1,127 @@
+use std::{net::SocketAddr, time::Duration};
+
+use anyhow::anyhow;
+use log::debug;
+use tokio::net::UdpSocket;
+
+#[cfg(feature = "mdns")]
+use super::mdns::{self, Advertisement};
+use super::{
+    Transport, TransportConnection,
+    demux::{self, Demux, PeerQueue},
+    net, pin_line,
+};
+use crate::{
+    app::{Args, PeerAddr, check_access},
+    input::Message,
+};
+
+#[derive(Default)]
+pub struct UdpTransport {
+    sockets: Vec<UdpSocket>,
+    peers: Demux<SocketAddr>,
+    #[cfg(feature = "mdns")]
+    advertisement: Option<Advertisement>,
+}
+
+impl UdpTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Transport for UdpTransport {
+    type Connection = UdpTransportConnection;
+
+    async fn listen(&mut self, args: Args) -> anyhow::Result<()> {
+        if !self.sockets.is_empty() {
+            return Ok(());
+        }
+
+        let addrs = net::resolve(&args.bind, args.udp_port)?;
+        self.sockets = net::bind_udp(&addrs)?;
+        net::log_reachable("udp", &addrs);
+        demux::warn_if_throttled("UDP", &args);
+        #[cfg(feature = "mdns")]
+        {
+            self.advertisement = mdns::advertise(mdns::UDP_SERVICE, "udp", &addrs, &args, &[]);
+        }
+
+        Ok(())
+    }
+
+    /// Receives datagrams until one arrives from an unknown sender, routing
+    /// everything from known senders to their pseudo-connection
+    async fn accept(&mut self, args: Args) -> anyhow::Result<Self::Connection> {
+        if self.sockets.is_empty() {
+            return Err(anyhow!("UDP transport is not listening"));
+        }
+        let mut buf = vec![0; u16::MAX as usize];
+
+        loop {
+            let (len, peer_addr) = net::recv_any(&self.sockets, &mut buf).await?;
+            // Every datagram of a rejected sender is checked again, so this
+            // stays at debug to not flood the log
+            if let Err(err) = check_access(&args, PeerAddr::Ip(peer_addr.ip())) {
+                debug!("{err}");
+                continue;
+            }
+            if let Some(queue) = self.peers.route(peer_addr, buf[..len].to_vec())? {
+                return Ok(UdpTransportConnection {
+                    queue,
+                    timeout: Duration::from_millis(args.udp_timeout_ms),
+                });
+            }
+        }
+    }
+}
+
+/// Every datagram from one source address, ended after a period without any
+pub struct UdpTransportConnection {
+    queue: PeerQueue<SocketAddr>,
+    timeout: Duration,
+}
+
+impl UdpTransportConnection {
+    /// Waits for the next non-empty datagram, or None once the sender went quiet
+    async fn recv_text(&mut self) -> Option<String> {
+        loop {
+            let datagram = self.queue.recv(self.timeout).await?;
+            let text = String::from_utf8_lossy(&datagram);
+            let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+            if !text.is_empty() {
+                return Some(text.to_string());
+            }
+        }
+    }
+}
+
+impl TransportConnection for UdpTransportConnection {
+    fn peer_name(&self) -> String {
+        format!("droidpad-{}", self.queue.key().ip())
+    }
+
+    fn peer_id(&self) -> String {
+        format!("ip:{}", self.queue.key().ip())
+    }
+
+    fn peer_address(&self) -> Option<String> {
+        Some(self.queue.key().ip().to_canonical().to_string())
+    }
+
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(self.recv_text().await.as_deref().and_then(pin_line))
+    }
+
+    async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
+        let Some(text) = self.recv_text().await else {
+            return Ok(None);
+        };
+
+        Ok(Some(Message::decode(&text)?))
+    }
+
+    fn reject(&mut self) {
+        self.queue.reject();
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    fs::{self, DirBuilder, Permissions},
+    os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+use log::{info, warn};
+use rand::Rng;
+use tokio::{
+    io::{AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+use super::{Transport, TransportConnection, pin_line, read_line};
+use crate::{app::Args, input::Message};
+
+/// Name of the socket in `$XDG_RUNTIME_DIR` when `--unix-socket` isn't set
+const SOCKET_NAME: &str = "droidpad-gamepad.sock";
+
+#[derive(Default)]
+pub struct UnixTransport {
+    listener: Option<UnixListener>,
+    /// Socket file created by `listen`, removed again on drop
+    path: Option<PathBuf>,
+}
+
+impl UnixTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Drop for UnixTransport {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path
+            && let Err(err) = fs::remove_file(path)
+        {
+            warn!("Failed to remove {}: {err}", path.display());
+        }
+    }
+}
+
+impl Transport for UnixTransport {
+    type Connection = UnixTransportConnection;
+
+    async fn listen(&mut self, args: Args) -> anyhow::Result<()> {
+        if self.listener.is_some() {
+            return Ok(());
+        }
+
+        let path = socket_path(&args)?;
+        let mode = u32::from_str_radix(args.unix_socket_mode.trim(), 8)
+            .ok()
+            .filter(|mode| *mode <= 0o777)
+            .ok_or_else(|| {
+                anyhow!(
+                    "`{}` is not an octal file mode such as 600",
+                    args.unix_socket_mode
+                )
+            })?;
+        remove_stale(&path).await?;
+
+        let listener = bind_private(&path, mode)?;
+        self.path = Some(path.clone());
+        info!("Listening on {} (mode {mode:03o})", path.display());
+        self.listener = Some(listener);
+
+        Ok(())
+    }
+
+    async fn accept(&mut self, _args: Args) -> anyhow::Result<Self::Connection> {
+        let Some(listener) = &self.listener else {
+            return Err(anyhow!("Unix socket transport is not listening"));
+        };
+        let (stream, _) = listener.accept().await?;
+
+        Ok(UnixTransportConnection::new(stream))
+    }
+}
+
+fn socket_path(args: &Args) -> anyhow::Result<PathBuf> {
+    match &args.unix_socket {
+        Some(path) => Ok(path.clone()),
+        None => dirs::runtime_dir()
+            .map(|dir| dir.join(SOCKET_NAME))
+            .ok_or_else(|| anyhow!("$XDG_RUNTIME_DIR is not set, pass --unix-socket")),
+    }
+}
+
+/// Binds inside a new directory only the current user can enter and moves
+/// the socket into place once it has its mode, so it's never reachable with
+/// the umask's mode in between
+fn bind_private(path: &Path, mode: u32) -> anyhow::Result<UnixListener> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a socket path", path.display()))?;
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let dir = parent.join(format!(
+        ".{}.{:08x}",
+        name.to_string_lossy(),
+        rand::rng().random::<u32>()
+    ));
+    DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let staged = dir.join(name);
+    let bound = (|| -> anyhow::Result<UnixListener> {
+        let listener = UnixListener::bind(&staged)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+        fs::set_permissions(&staged, Permissions::from_mode(mode))
+            .with_context(|| format!("Failed to set the mode of {}", path.display()))?;
+        fs::rename(&staged, path)
+            .with_context(|| format!("Failed to move the socket to {}", path.display()))?;
+        Ok(listener)
+    })();
+    if let Err(err) = fs::remove_dir_all(&dir) {
+        warn!("Failed to remove {}: {err}", dir.display());
+    }
+    bound
+}
+
+/// Removes a socket left behind by a server that didn't shut down cleanly.
+/// Fails when another server still answers on it, or when the path is
+/// something other than a socket
+async fn remove_stale(path: &Path) -> anyhow::Result<()> {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(anyhow!("{} exists and is not a socket", path.display()));
+    }
+    if UnixStream::connect(path).await.is_ok() {
+        return Err(anyhow!(
+            "Another server is already listening on {}",
+            path.display()
+        ));
+    }
+
+    fs::remove_file(path).with_context(|| format!("Failed to remove stale {}", path.display()))
+}
+
+pub struct UnixTransportConnection {
+    reader: BufReader<UnixStream>,
+    /// User and process on the other end, when the platform tells
+    uid: Option<u32>,
+    pid: Option<i32>,
+}
+
+impl UnixTransportConnection {
+    fn new(stream: UnixStream) -> Self {
+        let credentials = stream.peer_cred().ok();
+        Self {
+            reader: BufReader::new(stream),
+            uid: credentials.map(|credentials| credentials.uid()),
+            pid: credentials.and_then(|credentials| credentials.pid()),
+        }
+    }
+}
+
+impl TransportConnection for UnixTransportConnection {
+    fn peer_name(&self) -> String {
+        match self.pid {
+            Some(pid) => format!("droidpad-unix-{pid}"),
+            None => String::from("droidpad-unix"),
+        }
+    }
+
+    fn peer_id(&self) -> String {
+        match self.uid {
+            Some(uid) => format!("unix:{uid}"),
+            None => String::from("unix"),
+        }
+    }
+
+    /// Access lists don't apply, the socket's file mode decides who connects
+    fn peer_address(&self) -> Option<String> {
+        None
+    }
+
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(read_line(&mut self.reader)
+            .await?
+            .as_deref()
+            .and_then(pin_line))
+    }
+
+    async fn send_token(&mut self, token: &str) -> anyhow::Result<bool> {
+        let stream = self.reader.get_mut();
+        stream
+            .write_all(format!("token,{token}\n").as_bytes())
+            .await?;
+        stream.flush().await?;
+        Ok(true)
+    }
+
+    async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
+        let Some(line) = read_line(&mut self.reader).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Message::decode(&line)?))
+    }
+}
@@ -1,94 +1,405 @@
-use std::net::SocketAddr;
+use std::{collections::VecDeque, net::SocketAddr, time::Duration};
 
 use anyhow::{Context, anyhow};
-use futures_util::StreamExt;
-use log::info;
-use tokio::net::{TcpListener, TcpStream};
-use tokio_tungstenite::{WebSocketStream, accept_async, tungstenite::Message as WsMessage};
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+    time::{Instant, Interval, MissedTickBehavior},
+};
+#[cfg(feature = "tls")]
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::{
+    WebSocketStream, accept_hdr_async,
+    tungstenite::{
+        Message as WsMessage,
+        handshake::server::{Request, Response},
+        http::{HeaderValue, header::SEC_WEBSOCKET_PROTOCOL},
+    },
+};
 
-use super::{Transport, TransportConnection};
-use crate::{app::Args, input::Message};
+#[cfg(feature = "mdns")]
+use super::mdns::{self, Advertisement};
+#[cfg(feature = "tls")]
+use super::tls::TlsIdentity;
+use super::{Transport, TransportConnection, handshakes::Handshakes, net};
+use crate::{
+    app::Args,
+    input::{InvalidMessage, Message, compact},
+};
+
+/// Header carrying the pairing PIN for clients that can't put it in the URL
+const PIN_HEADER: &str = "X-DroidPad-Pin";
+
+/// Plain TCP or TLS, whichever the listener was started with
+trait WsStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> WsStream for T {}
 
 #[derive(Default)]
 pub struct WsTransport {
-    listener: Option<TcpListener>,
+    listeners: Vec<TcpListener>,
+    handshakes: Handshakes<WsTransportConnection>,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsIdentity>,
+    #[cfg(feature = "mdns")]
+    advertisement: Option<Advertisement>,
 }
 
 impl WsTransport {
     pub fn new() -> Self {
         Self::default()
     }
+
+    #[cfg(feature = "tls")]
+    pub fn tls_fingerprint(&self) -> Option<String> {
+        self.tls.as_ref().map(|tls| tls.fingerprint.clone())
+    }
+
+    #[cfg(not(feature = "tls"))]
+    pub fn tls_fingerprint(&self) -> Option<String> {
+        None
+    }
 }
 
 impl Transport for WsTransport {
     type Connection = WsTransportConnection;
 
     async fn listen(&mut self, args: Args) -> anyhow::Result<()> {
-        if self.listener.is_some() {
+        if !self.listeners.is_empty() {
             return Ok(());
         }
 
-        let listener = TcpListener::bind(std::format!("0.0.0.0:{}", args.port)).await?;
-        info!(
-            "Listening on: {}:{}",
-            local_ip_address::local_ip()
-                .map(|v| v.to_string())
-                .unwrap_or(String::from("local_ip")),
-            args.port
-        );
-        self.listener = Some(listener);
+        #[cfg(feature = "tls")]
+        if args.tls {
+            self.tls = Some(TlsIdentity::load(&args)?);
+        }
+
+        let addrs = net::resolve(&args.bind, args.port)?;
+        self.listeners = net::bind_tcp(&addrs)?;
+        let scheme = if self.tls_fingerprint().is_some() {
+            "wss"
+        } else {
+            "ws"
+        };
+        net::log_reachable(scheme, &addrs);
+        #[cfg(feature = "mdns")]
+        {
+            self.advertisement = mdns::advertise(
+                mdns::TCP_SERVICE,
+                scheme,
+                &addrs,
+                &args,
+                &[("subprotocol", compact::PROTOCOL.to_string())],
+            );
+        }
 
         Ok(())
     }
 
-    async fn accept(&mut self, _args: Args) -> anyhow::Result<Self::Connection> {
-        let listener = self
-            .listener
-            .as_mut()
-            .ok_or_else(|| anyhow!("WebSocket transport is not listening"))?;
-        let (stream, peer_addr) = listener.accept().await?;
-        let socket = accept_async(stream).await?;
+    /// Yields the next connection whose handshake completed. Handshakes run
+    /// in their own tasks, so a slow client doesn't hold up the others
+    async fn accept(&mut self, args: Args) -> anyhow::Result<Self::Connection> {
+        if self.listeners.is_empty() {
+            return Err(anyhow!("WebSocket transport is not listening"));
+        }
+
+        loop {
+            tokio::select! {
+                connection = self.handshakes.next() => return Ok(connection),
+                accepted = net::accept_allowed(&self.listeners, &args),
+                    if self.handshakes.has_room(&args) =>
+                {
+                    let (stream, peer_addr) = accepted?;
+                    self.handshakes.spawn(
+                        peer_addr,
+                        &args,
+                        handshake(
+                            stream,
+                            peer_addr,
+                            Heartbeat::new(&args),
+                            #[cfg(feature = "tls")]
+                            self.tls.as_ref().map(|tls| tls.acceptor.clone()),
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+// The handshake callback's error type is defined by tungstenite
+#[allow(clippy::result_large_err)]
+async fn handshake(
+    stream: TcpStream,
+    peer_addr: SocketAddr,
+    heartbeat: Heartbeat,
+    #[cfg(feature = "tls")] tls: Option<TlsAcceptor>,
+) -> anyhow::Result<WsTransportConnection> {
+    #[cfg(feature = "tls")]
+    let stream: Box<dyn WsStream> = match tls {
+        Some(acceptor) => Box::new(
+            acceptor
+                .accept(stream)
+                .await
+                .context("TLS handshake failed")?,
+        ),
+        None => Box::new(stream),
+    };
+    #[cfg(not(feature = "tls"))]
+    let stream: Box<dyn WsStream> = Box::new(stream);
+
+    let mut client_id = None;
+    let mut pin = None;
+    let mut is_compact = false;
+    let socket = accept_hdr_async(stream, |request: &Request, mut response: Response| {
+        let query = request.uri().query();
+        // Ends up in device names, so only short printable ids are taken
+        client_id = query
+            .and_then(|query| query_param(query, "id"))
+            .filter(|id| !id.is_empty() && id.len() <= 32 && !id.contains(char::is_control));
+        pin = query
+            .and_then(|query| query_param(query, "pin"))
+            .or_else(|| {
+                request
+                    .headers()
+                    .get(PIN_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from)
+            });
+        is_compact = request
+            .headers()
+            .get_all(SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|protocol| protocol.trim() == compact::PROTOCOL);
+        if is_compact {
+            response.headers_mut().insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_static(compact::PROTOCOL),
+            );
+        }
+        Ok(response)
+    })
+    .await?;
+
+    Ok(WsTransportConnection::new(
+        socket, peer_addr, client_id, pin, is_compact, heartbeat,
+    ))
+}
+
+/// Returns the value of `key` in a `a=1&b=2` query string
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| percent_decode(value))
+        .filter(|value| !value.is_empty())
+}
 
-        Ok(WsTransportConnection::new(socket, peer_addr))
+/// Undoes URL encoding, `%XX` escapes and `+` for spaces. Malformed escapes
+/// are kept as they are
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', None) => decoded.push(b' '),
+            (byte, None) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// How often clients get pinged, and how long they may stay silent before
+/// the link counts as dead
+#[derive(Clone, Copy)]
+struct Heartbeat {
+    interval: Option<Duration>,
+    timeout: Duration,
+}
+
+impl Heartbeat {
+    fn new(args: &Args) -> Self {
+        Self {
+            interval: (args.heartbeat_interval_ms > 0)
+                .then(|| Duration::from_millis(args.heartbeat_interval_ms)),
+            timeout: Duration::from_millis(args.heartbeat_timeout_ms),
+        }
     }
 }
 
 pub struct WsTransportConnection {
-    socket: WebSocketStream<TcpStream>,
+    socket: WebSocketStream<Box<dyn WsStream>>,
     peer_addr: SocketAddr,
+    client_id: Option<String>,
+    pin: Option<String>,
+    /// Whether the client negotiated compact binary frames
+    is_compact: bool,
+    /// Updates from a compact frame that weren't handed out yet
+    batch: VecDeque<Message>,
+    pings: Option<Interval>,
+    heartbeat_timeout: Duration,
+    /// When anything, pongs included, last arrived
+    last_seen: Instant,
 }
 
 impl WsTransportConnection {
-    pub fn new(socket: WebSocketStream<TcpStream>, peer_addr: SocketAddr) -> Self {
-        Self { socket, peer_addr }
+    fn new(
+        socket: WebSocketStream<Box<dyn WsStream>>,
+        peer_addr: SocketAddr,
+        client_id: Option<String>,
+        pin: Option<String>,
+        is_compact: bool,
+        heartbeat: Heartbeat,
+    ) -> Self {
+        let pings = heartbeat.interval.map(|interval| {
+            let mut pings = tokio::time::interval_at(Instant::now() + interval, interval);
+            pings.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            pings
+        });
+        Self {
+            socket,
+            peer_addr,
+            client_id,
+            pin,
+            is_compact,
+            batch: VecDeque::new(),
+            pings,
+            heartbeat_timeout: heartbeat.timeout,
+            last_seen: Instant::now(),
+        }
+    }
+
+    /// Pings the client, failing once it has been silent for longer than the
+    /// heartbeat timeout
+    async fn heartbeat(&mut self) -> anyhow::Result<()> {
+        if self.last_seen.elapsed() >= self.heartbeat_timeout {
+            return Err(anyhow!(
+                "{} stopped answering pings, treating the link as dead",
+                self.peer_name()
+            ));
+        }
+        self.socket
+            .send(WsMessage::Ping(Default::default()))
+            .await
+            .context("Failed to ping websocket client")
+    }
+}
+
+/// Resolves on the next ping, never when pings are off
+async fn next_ping(pings: &mut Option<Interval>) {
+    match pings {
+        Some(pings) => {
+            pings.tick().await;
+        }
+        None => std::future::pending().await,
     }
 }
 
 impl TransportConnection for WsTransportConnection {
     fn peer_name(&self) -> String {
-        format!("droidpad-{}", self.peer_addr.ip())
+        match &self.client_id {
+            Some(client_id) => format!("droidpad-{client_id}"),
+            None => format!("droidpad-{}", self.peer_addr.ip()),
+        }
+    }
+
+    fn peer_id(&self) -> String {
+        format!("ip:{}", self.peer_addr.ip())
+    }
+
+    fn slot_hint(&self) -> String {
+        match &self.client_id {
+            Some(client_id) => format!("client:{client_id}"),
+            None => self.peer_id(),
+        }
+    }
+
+    fn peer_address(&self) -> Option<String> {
+        Some(self.peer_addr.ip().to_canonical().to_string())
+    }
+
+    async fn recv_pin(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(self.pin.take())
+    }
+
+    async fn send_token(&mut self, token: &str) -> anyhow::Result<bool> {
+        self.socket
+            .send(WsMessage::Text(format!("token,{token}").into()))
+            .await
+            .context("Failed to send the pairing token")?;
+        Ok(true)
     }
 
     async fn recv_message(&mut self) -> anyhow::Result<Option<Message>> {
         loop {
-            let Some(message) = self.socket.next().await else {
+            if let Some(message) = self.batch.pop_front() {
+                return Ok(Some(message));
+            }
+
+            let message = tokio::select! {
+                message = self.socket.next() => message,
+                () = next_ping(&mut self.pings) => {
+                    self.heartbeat().await?;
+                    continue;
+                }
+            };
+            let Some(message) = message else {
                 return Ok(None);
             };
             let message = message?;
+            self.last_seen = Instant::now();
 
             match message {
                 WsMessage::Text(text) => {
-                    let parsed = serde_json::from_str::<Message>(text.as_ref())
-                        .context("Failed to parse websocket message as DroidPad JSON")?;
-                    return Ok(Some(parsed));
+                    return Ok(Some(Message::decode(&text)?));
                 }
                 WsMessage::Close(_) => return Ok(None),
                 WsMessage::Ping(_) | WsMessage::Pong(_) => continue,
+                WsMessage::Binary(frame) if self.is_compact => {
+                    compact::decode(&frame, &mut self.batch)
+                        .context("Failed to decode compact websocket frame")
+                        .map_err(InvalidMessage)?;
+                }
                 WsMessage::Binary(_) => {
-                    return Err(anyhow!("Binary websocket messages are not supported"));
+                    return Err(InvalidMessage(anyhow!(
+                        "Binary websocket messages need the {} subprotocol",
+                        compact::PROTOCOL
+                    ))
+                    .into());
                 }
                 WsMessage::Frame(_) => continue,
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_query_values() {
+        let query = "id=Pixel%208%20Pro&pin=48+29%2613&empty=&bad=%zz%4";
+        assert_eq!(query_param(query, "id").as_deref(), Some("Pixel 8 Pro"));
+        assert_eq!(query_param(query, "pin").as_deref(), Some("48 29&13"));
+        assert_eq!(query_param(query, "empty"), None);
+        assert_eq!(query_param(query, "bad").as_deref(), Some("%zz%4"));
+        assert_eq!(query_param(query, "missing"), None);
+    }
+}
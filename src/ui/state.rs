@@ -1,33 +1,56 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
 use gpui::{AnyWindowHandle, AppContext, Context, Entity, Task};
 use gpui_component::{ActiveTheme, ThemeMode};
-use log::error;
+use log::{error, info, warn};
 use tokio::sync::{mpsc, watch};
 
+#[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+use crate::app::{QrModules, connection_info};
 use crate::{
-    app::{Args, SettingsManager, TransportKind, serve_transport_loop, start_transport},
+    app::{
+        ApprovalRequest, Args, Decision, PeerInfo, SettingsManager, TransportKind, block_address,
+        disconnect_peer, serve_transport_loop, start_transport,
+    },
     ui::tray::Tray,
 };
 
 pub struct Data {
     pub settings: Args,
     pub theme_mode: Option<ThemeMode>,
-    pub running_transport: Option<RunningTransport>,
+    pub running_transports: BTreeMap<TransportKind, RunningTransport>,
     pub main_window: Option<AnyWindowHandle>,
     pub tray: Option<Entity<Tray>>,
     pub next_transport_id: u64,
+    /// New phones waiting to be allowed or denied, oldest first
+    pub pending_approvals: VecDeque<ApprovalRequest>,
+    /// QR code of the live transports' URLs, kept until they change
+    #[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+    pub connection_qr: Option<QrModules>,
 }
 
 pub struct RunningTransport {
     pub id: u64,
     pub shutdown: watch::Sender<bool>,
     pub task: Task<()>,
-    pub kind: TransportKind,
     pub is_live: bool,
+    pub tls_fingerprint: Option<String>,
 }
 
 enum TransportEvent {
-    Started { id: u64 },
-    Stopped { id: u64, error: Option<String> },
+    Started {
+        kind: TransportKind,
+        id: u64,
+        tls_fingerprint: Option<String>,
+    },
+    Stopped {
+        kind: TransportKind,
+        id: u64,
+        error: Option<String>,
+    },
 }
 
 impl Data {
@@ -35,10 +58,13 @@ impl Data {
         Self {
             settings,
             theme_mode,
-            running_transport: None,
+            running_transports: BTreeMap::new(),
             main_window: None,
             tray,
             next_transport_id: 0,
+            pending_approvals: VecDeque::new(),
+            #[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+            connection_qr: None,
         }
     }
 
@@ -48,6 +74,62 @@ impl Data {
         }
     }
 
+    /// Makes every paired phone present the PIN again on its next connection
+    pub fn forget_paired_peers(&self) {
+        if let Err(err) = SettingsManager::forget_pairing_tokens() {
+            error!("Failed to forget paired phones: {err}");
+        }
+    }
+
+    /// Makes every phone with a remembered approval answer ask again
+    pub fn forget_peer_policies(&self) {
+        if let Err(err) = SettingsManager::forget_peer_policies() {
+            error!("Failed to forget approval answers: {err}");
+        }
+    }
+
+    /// Disconnects a peer and rejects its address from now on, keeping the
+    /// block in the settings for the next start
+    pub fn block_peer(&mut self, peer: &PeerInfo, cx: &mut Context<Self>) {
+        if let Some(address) = &peer.address {
+            block_address(address);
+            if !self.settings.blocklist.contains(address) {
+                self.settings.blocklist.push(address.clone());
+                self.persist_settings();
+            }
+            info!("Blocked {} ({address})", peer.name);
+        }
+        disconnect_peer(peer.id);
+        cx.notify();
+    }
+
+    /// Queues an approval prompt, dropping it again once its connection
+    /// stops waiting
+    pub fn push_approval(&mut self, request: ApprovalRequest, cx: &mut Context<Self>) {
+        let timeout = Duration::from_millis(self.settings.approval_timeout_ms);
+        self.pending_approvals.push_back(request);
+        cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(timeout).await;
+            let _ = this.update(cx, |data, cx| {
+                data.pending_approvals
+                    .retain(|request| !request.is_expired());
+                cx.notify();
+            });
+        })
+        .detach();
+        cx.notify();
+    }
+
+    /// Answers the oldest approval prompt
+    pub fn answer_approval(&mut self, decision: Decision, cx: &mut Context<Self>) {
+        if let Some(request) = self.pending_approvals.pop_front() {
+            request.answer(decision);
+        }
+        self.pending_approvals
+            .retain(|request| !request.is_expired());
+        cx.notify();
+    }
+
     pub fn set_theme_mode(&mut self, theme_mode: ThemeMode) {
         self.theme_mode = Some(theme_mode);
         if let Err(err) = SettingsManager::save_theme_mode(theme_mode) {
@@ -73,37 +155,45 @@ impl Data {
     ) {
         update(&mut self.settings);
         self.persist_settings();
+        self.refresh_connection_qr();
 
-        if restart_transport && self.running_transport.is_some() {
-            self.restart_transport(cx);
+        if restart_transport && !self.running_transports.is_empty() {
+            self.restart_transports(cx);
         } else {
             cx.notify();
         }
     }
 
-    pub fn restart_transport(&mut self, cx: &mut Context<Self>) {
-        if let Some(running_transport) = self.running_transport.take() {
+    pub fn restart_transports(&mut self, cx: &mut Context<Self>) {
+        let kinds = self.running_transports.keys().copied().collect::<Vec<_>>();
+        for kind in kinds {
+            self.restart_transport(kind, cx);
+        }
+    }
+
+    pub fn restart_transport(&mut self, kind: TransportKind, cx: &mut Context<Self>) {
+        if let Some(running_transport) = self.running_transports.remove(&kind) {
             let entity = cx.entity().clone();
             cx.spawn(async move |_, cx| {
                 let _ = running_transport.shutdown.send(true);
                 running_transport.task.await;
 
                 entity.update(cx, |data, cx| {
-                    if data.running_transport.is_none() {
-                        data.start_transport(cx);
+                    if !data.running_transports.contains_key(&kind) {
+                        data.start_transport(kind, cx);
                     }
                 });
             })
             .detach();
         } else {
-            self.start_transport(cx);
+            self.start_transport(kind, cx);
         }
 
         cx.notify();
     }
 
-    pub fn start_transport(&mut self, cx: &mut Context<Self>) {
-        if self.running_transport.is_some() {
+    pub fn start_transport(&mut self, kind: TransportKind, cx: &mut Context<Self>) {
+        if self.running_transports.contains_key(&kind) {
             return;
         }
 
@@ -111,7 +201,6 @@ impl Data {
 
         self.next_transport_id += 1;
         let id = self.next_transport_id;
-        let kind = self.settings.transport;
         let args = self.settings.clone();
         let (shutdown, receiver) = watch::channel(false);
         let (events_tx, mut events_rx) = mpsc::unbounded_channel();
@@ -123,6 +212,7 @@ impl Data {
                 Ok(runtime) => runtime,
                 Err(err) => {
                     let _ = events_tx.send(TransportEvent::Stopped {
+                        kind,
                         id,
                         error: Some(format!("Failed to create transport runtime: {err}")),
                     });
@@ -131,17 +221,22 @@ impl Data {
             };
 
             let result = runtime.block_on(async {
-                let transport = start_transport(&args).await?;
-                let _ = events_tx.send(TransportEvent::Started { id });
-                serve_transport_loop(transport, args, Some(receiver)).await?;
+                let transport = start_transport(&args, kind).await?;
+                let _ = events_tx.send(TransportEvent::Started {
+                    kind,
+                    id,
+                    tls_fingerprint: transport.tls_fingerprint(),
+                });
+                serve_transport_loop(transport, kind, args, Some(receiver)).await?;
                 Ok::<(), anyhow::Error>(())
             });
 
             let _ = events_tx.send(TransportEvent::Stopped {
+                kind,
                 id,
                 error: result
                     .err()
-                    .map(|err| format!("Transport service exited with error: {err}")),
+                    .map(|err| format!("Transport {kind:?} exited with error: {err}")),
             });
         });
 
@@ -154,36 +249,111 @@ impl Data {
         })
         .detach();
 
-        self.running_transport = Some(RunningTransport {
-            id,
-            shutdown,
-            task,
+        self.running_transports.insert(
             kind,
-            is_live: false,
-        });
+            RunningTransport {
+                id,
+                shutdown,
+                task,
+                is_live: false,
+                tls_fingerprint: None,
+            },
+        );
         cx.notify();
     }
 
-    pub fn stop_transport(&mut self) {
-        if let Some(running_transport) = self.running_transport.take() {
+    /// Starts every enabled transport that isn't running yet
+    pub fn start_transports(&mut self, cx: &mut Context<Self>) {
+        for kind in self.settings.transports.clone() {
+            self.start_transport(kind, cx);
+        }
+    }
+
+    pub fn stop_transport(&mut self, kind: TransportKind) {
+        if let Some(running_transport) = self.running_transports.remove(&kind) {
+            let _ = running_transport.shutdown.send(true);
+        }
+        self.refresh_connection_qr();
+    }
+
+    pub fn stop_transports(&mut self) {
+        for (_, running_transport) in std::mem::take(&mut self.running_transports) {
             let _ = running_transport.shutdown.send(true);
         }
+        self.refresh_connection_qr();
     }
 
-    pub fn toggle_transport(&mut self, cx: &mut Context<Self>) {
-        if self.running_transport.is_some() {
-            self.stop_transport();
+    /// Recomputes the connection QR code, whenever a transport goes live or
+    /// stops or the settings it encodes may have changed
+    #[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+    fn refresh_connection_qr(&mut self) {
+        let live_transports = self
+            .running_transports
+            .iter()
+            .filter(|(_, transport)| transport.is_live)
+            .map(|(kind, _)| *kind);
+        self.connection_qr = connection_info(&self.settings, live_transports).and_then(|info| {
+            QrModules::new(&info)
+                .map_err(|err| warn!("Failed to render the connection QR code: {err}"))
+                .ok()
+        });
+    }
+
+    #[cfg(not(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp"))))]
+    fn refresh_connection_qr(&mut self) {}
+
+    /// Starts or stops every enabled transport together
+    pub fn toggle_transports(&mut self, cx: &mut Context<Self>) {
+        if !self.running_transports.is_empty() {
+            self.stop_transports();
             cx.notify();
             return;
         }
 
-        self.start_transport(cx);
+        self.start_transports(cx);
+    }
+
+    /// Starts or stops one transport without touching the others, keeping the
+    /// enabled set in line so the next start brings back the same transports
+    pub fn toggle_transport(&mut self, kind: TransportKind, cx: &mut Context<Self>) {
+        let enable = !self.running_transports.contains_key(&kind);
+        self.set_transport_enabled(kind, enable, cx);
+        if enable {
+            self.start_transport(kind, cx);
+        }
+    }
+
+    /// Adds or removes a transport from the enabled set. While the service is
+    /// running the transport is started or stopped right away
+    pub fn set_transport_enabled(
+        &mut self,
+        kind: TransportKind,
+        enabled: bool,
+        cx: &mut Context<Self>,
+    ) {
+        let transports = &mut self.settings.transports;
+        if enabled && !transports.contains(&kind) {
+            transports.push(kind);
+            transports.sort();
+        } else if !enabled {
+            transports.retain(|transport| *transport != kind);
+        }
+        self.persist_settings();
+
+        if self.running_transports.is_empty() {
+            cx.notify();
+        } else if enabled {
+            self.start_transport(kind, cx);
+        } else {
+            self.stop_transport(kind);
+            cx.notify();
+        }
     }
 }
 
 impl Drop for Data {
     fn drop(&mut self) {
-        self.stop_transport();
+        self.stop_transports();
     }
 }
 
@@ -194,6 +364,16 @@ impl TransportKind {
             Self::Ws => "WebSocket",
             #[cfg(feature = "bluetooth")]
             Self::Bluetooth => "Bluetooth",
+            #[cfg(feature = "tcp")]
+            Self::Tcp => "TCP",
+            #[cfg(feature = "udp")]
+            Self::Udp => "UDP",
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt => "MQTT",
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix => "Unix socket",
+            #[cfg(feature = "serial")]
+            Self::Serial => "Serial",
         }
     }
 
@@ -203,6 +383,16 @@ impl TransportKind {
             Self::Ws,
             #[cfg(feature = "bluetooth")]
             Self::Bluetooth,
+            #[cfg(feature = "tcp")]
+            Self::Tcp,
+            #[cfg(feature = "udp")]
+            Self::Udp,
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt,
+            #[cfg(all(unix, feature = "unix"))]
+            Self::Unix,
+            #[cfg(feature = "serial")]
+            Self::Serial,
         ]
     }
 }
@@ -210,19 +400,28 @@ impl TransportKind {
 impl Data {
     fn handle_transport_event(&mut self, event: TransportEvent, cx: &mut Context<Self>) {
         match event {
-            TransportEvent::Started { id } => {
-                if let Some(running_transport) = self.running_transport.as_mut()
+            TransportEvent::Started {
+                kind,
+                id,
+                tls_fingerprint,
+            } => {
+                if let Some(running_transport) = self.running_transports.get_mut(&kind)
                     && running_transport.id == id
                 {
                     running_transport.is_live = true;
+                    running_transport.tls_fingerprint = tls_fingerprint;
+                    self.refresh_connection_qr();
                     cx.notify();
                 }
             }
-            TransportEvent::Stopped { id, error } => {
-                if let Some(running_transport) = self.running_transport.as_ref()
-                    && running_transport.id == id
+            TransportEvent::Stopped { kind, id, error } => {
+                if self
+                    .running_transports
+                    .get(&kind)
+                    .is_some_and(|running_transport| running_transport.id == id)
                 {
-                    self.running_transport = None;
+                    self.running_transports.remove(&kind);
+                    self.refresh_connection_qr();
                     if let Some(error) = error {
                         error!("{error}");
                     }
@@ -1,15 +1,46 @@
+use std::{
+    collections::BTreeSet,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
 use gpui::{AppContext, Context, EventEmitter};
 use log::{error, warn};
 use tray_icon::{
     Icon, TrayIcon, TrayIconBuilder,
-    menu::{Menu, MenuEvent, MenuId, MenuItem, PredefinedMenuItem},
+    menu::{CheckMenuItem, IsMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem},
 };
 
-pub struct Tray;
+use crate::app::TransportKind;
+
+/// How often the tray thread picks up transports started or stopped elsewhere
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+const SYNC_INTERVAL_MS: u32 = 100;
+
+pub struct Tray {
+    /// Transports last shown as checked
+    running: BTreeSet<TransportKind>,
+    updates: Sender<BTreeSet<TransportKind>>,
+}
+
+/// The tray menu's per-transport items, only usable on the tray thread
+struct TransportItems(Vec<(TransportKind, CheckMenuItem)>);
+
+impl TransportItems {
+    /// Checks the items of the transports that run now, using only the
+    /// latest of the pending updates
+    fn sync(&self, updates: &Receiver<BTreeSet<TransportKind>>) {
+        let Some(running) = updates.try_iter().last() else {
+            return;
+        };
+        for (kind, item) in &self.0 {
+            item.set_checked(running.contains(kind));
+        }
+    }
+}
 
 pub enum TrayEvent {
-    StartWS,
-    StartBluetooth,
+    /// Starts or stops one transport, independently of the others
+    ToggleTransport(TransportKind),
     ToggleWindow,
     Exit,
 }
@@ -18,23 +49,37 @@ impl EventEmitter<TrayEvent> for Tray {}
 
 static TOGGLE_WINDOW_ID: &str = "toggle_window";
 static EXIT_ID: &str = "exit";
-static START_WS: &str = "start_ws";
-static START_BLUETOOTH: &str = "start_bluetooth";
+
+fn transport_menu_id(kind: TransportKind) -> String {
+    format!("transport_{kind:?}")
+}
 
 impl Tray {
-    fn new_tray_icon() -> Option<TrayIcon> {
+    fn new_tray_icon() -> Option<(TrayIcon, TransportItems)> {
         let tray_menu = Menu::new();
-        let start_ws = MenuItem::with_id(START_WS, "Start websocket", true, None);
-        let start_bluetooth = MenuItem::with_id(START_BLUETOOTH, "Start bluetooth", true, None);
+        let transport_items = TransportKind::all()
+            .iter()
+            .map(|kind| {
+                let item = CheckMenuItem::with_id(
+                    transport_menu_id(*kind),
+                    kind.label(),
+                    true,
+                    false,
+                    None,
+                );
+                (*kind, item)
+            })
+            .collect::<Vec<_>>();
+        let separator = PredefinedMenuItem::separator();
         let toggle_item = MenuItem::with_id(TOGGLE_WINDOW_ID, "Toggle Window", true, None);
         let exit_item = MenuItem::with_id(EXIT_ID, "Exit", true, None);
-        if let Err(err) = tray_menu.append_items(&[
-            &start_ws,
-            &start_bluetooth,
-            &PredefinedMenuItem::separator(),
-            &toggle_item,
-            &exit_item,
-        ]) {
+
+        let mut items = transport_items
+            .iter()
+            .map(|(_, item)| item as &dyn IsMenuItem)
+            .collect::<Vec<_>>();
+        items.extend([&separator as &dyn IsMenuItem, &toggle_item, &exit_item]);
+        if let Err(err) = tray_menu.append_items(&items) {
             error!("Failed to create tray menu: {err}");
             return None;
         }
@@ -44,14 +89,23 @@ impl Tray {
             .with_menu(Box::new(tray_menu))
             .build()
         {
-            Ok(icon) => Some(icon),
+            Ok(icon) => Some((icon, TransportItems(transport_items))),
             Err(err) => {
                 error!("Failed to create tray icon: {err}");
                 None
             }
         }
     }
+    /// Checks the menu items of exactly the `running` transports
+    pub fn set_running(&mut self, running: BTreeSet<TransportKind>) {
+        if running != self.running {
+            self.running = running.clone();
+            let _ = self.updates.send(running);
+        }
+    }
+
     pub fn new(cx: &mut Context<Self>) -> Self {
+        let (updates, updates_rx) = mpsc::channel();
         std::thread::spawn(move || {
             use log::info;
             info!("Spawning bg thread for tray");
@@ -61,24 +115,34 @@ impl Tray {
                 return;
             }
 
-            let _icon = match Tray::new_tray_icon() {
-                Some(icon) => icon,
+            let (_icon, transport_items) = match Tray::new_tray_icon() {
+                Some(tray) => tray,
                 None => return,
             };
 
             #[cfg(target_os = "linux")]
             {
+                gtk::glib::timeout_add_local(
+                    std::time::Duration::from_millis(SYNC_INTERVAL_MS.into()),
+                    move || {
+                        transport_items.sync(&updates_rx);
+                        gtk::glib::ControlFlow::Continue
+                    },
+                );
                 gtk::main();
             }
             #[cfg(target_os = "windows")]
             {
                 use windows::Win32::UI::WindowsAndMessaging::{
-                    DispatchMessageW, GetMessageW, MSG, TranslateMessage,
+                    DispatchMessageW, GetMessageW, MSG, SetTimer, TranslateMessage,
                 };
 
                 let mut msg = MSG::default();
                 unsafe {
+                    // Wakes the loop up to pick up updates
+                    SetTimer(None, 0, SYNC_INTERVAL_MS, None);
                     while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                        transport_items.sync(&updates_rx);
                         let _ = TranslateMessage(&msg);
                         let _ = DispatchMessageW(&msg);
                     }
@@ -91,11 +155,15 @@ impl Tray {
             cx.background_spawn(async move {
                 while let Ok(evt) = MenuEvent::receiver().recv() {
                     let res = match evt.id {
-                        v if v == START_WS => menu_tx.send(TrayEvent::StartWS),
-                        v if v == START_BLUETOOTH => menu_tx.send(TrayEvent::StartBluetooth),
                         v if v == TOGGLE_WINDOW_ID => menu_tx.send(TrayEvent::ToggleWindow),
                         v if v == EXIT_ID => menu_tx.send(TrayEvent::Exit),
-                        MenuId(_) => Ok(()),
+                        v => match TransportKind::all()
+                            .iter()
+                            .find(|kind| v == transport_menu_id(**kind))
+                        {
+                            Some(kind) => menu_tx.send(TrayEvent::ToggleTransport(*kind)),
+                            None => Ok(()),
+                        },
                     };
 
                     if let Err(err) = res {
@@ -118,7 +186,10 @@ impl Tray {
         })
         .detach();
 
-        Self
+        Self {
+            running: BTreeSet::new(),
+            updates,
+        }
     }
 }
 
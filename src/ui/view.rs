@@ -13,9 +13,17 @@ use gpui_component::setting::{
     NumberFieldOptions, SettingField, SettingGroup, SettingItem, SettingPage, Settings,
 };
 use gpui_component::{ActiveTheme, Sizable, Size, StyledExt, Theme, ThemeMode};
+#[cfg(any(feature = "tls", all(unix, feature = "unix")))]
+use std::path::PathBuf;
 use std::rc::Rc;
 
-use crate::app::{Args, TransportKind};
+#[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+use crate::app::QrModules;
+use crate::app::{
+    Args, Decision, ParseErrorPolicy, PeerInfo, PeerPolicy, RateLimitAction, TransportKind,
+    connected_peers, disconnect_peer, pairing_pin,
+};
+use crate::controller::AxisPolicy;
 use crate::ui::state::Data;
 
 fn h_flex() -> gpui::Div {
@@ -31,30 +39,49 @@ impl Render for Data {
         let view = cx.entity().clone();
         let settings = self.settings.clone();
         let is_starting = self
-            .running_transport
-            .as_ref()
-            .is_some_and(|transport| !transport.is_live);
+            .running_transports
+            .values()
+            .any(|transport| !transport.is_live);
         let is_running = self
-            .running_transport
-            .as_ref()
-            .is_some_and(|transport| transport.is_live);
-        let has_transport_task = self.running_transport.is_some();
+            .running_transports
+            .values()
+            .any(|transport| transport.is_live);
+        let has_transport_task = !self.running_transports.is_empty();
         let is_dark_mode = self.effective_theme_mode(cx).is_dark();
-        let running_transport_label = self
-            .running_transport
-            .as_ref()
-            .map(|transport| {
-                if transport.is_live {
-                    transport.kind.label()
-                } else {
-                    "Starting"
-                }
-            })
-            .unwrap_or("Stopped");
+        let running_transport_label = if has_transport_task {
+            SharedString::from(
+                self.running_transports
+                    .iter()
+                    .map(|(kind, transport)| {
+                        if transport.is_live {
+                            kind.label().to_string()
+                        } else {
+                            format!("{} (starting)", kind.label())
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        } else {
+            SharedString::from("Stopped")
+        };
+        let tls_fingerprint = self
+            .running_transports
+            .values()
+            .find_map(|transport| transport.tls_fingerprint.clone())
+            .map(SharedString::from);
+        let pairing_pin = pairing_pin(&settings).map(SharedString::from);
+        let connection_qr = render_connection_qr(self, cx);
+        let pending_approval = self
+            .pending_approvals
+            .front()
+            .map(|request| SharedString::from(request.peer_name.clone()));
+        let queued_approvals = self.pending_approvals.len().saturating_sub(1);
+        let connected = connected_peers();
         let transport_button_label = if has_transport_task {
-            "Stop Transport"
+            "Stop Transports"
         } else {
-            "Start Transport"
+            "Start Transports"
         };
         let _status_color = if is_running {
             cx.theme().primary
@@ -78,31 +105,96 @@ impl Render for Data {
                                 .text_2xl()
                                 .font_weight(FontWeight::BOLD),
                         )
+                        .children(pending_approval.map(|peer_name| {
+                            render_approval_card(view.clone(), peer_name, queued_approvals, cx)
+                        }))
                         .child(render_service_card(
                             view.clone(),
-                            settings.transport,
+                            settings.transports.clone(),
                             running_transport_label,
+                            tls_fingerprint,
+                            pairing_pin,
+                            connection_qr,
                             transport_button_label,
                             is_starting,
                             is_running,
                             cx,
                         ))
+                        .children((!connected.is_empty()).then(|| {
+                            render_peers_card(view.clone(), connected, settings.max_connections, cx)
+                        }))
                         .child(render_settings_shell(view, settings, is_dark_mode, cx)),
                 ),
             )
     }
 }
 
+fn render_approval_card(
+    view: Entity<Data>,
+    peer_name: SharedString,
+    queued_approvals: usize,
+    cx: &mut Context<Data>,
+) -> impl IntoElement {
+    let answer_button = |id: &'static str, label: &'static str, decision: Decision| {
+        let view = view.clone();
+        Button::new(id).label(label).on_click(move |_, _, cx| {
+            view.update(cx, |data, cx| data.answer_approval(decision, cx));
+        })
+    };
+
+    v_flex()
+        .w_full()
+        .p_5()
+        .gap_3()
+        .rounded(cx.theme().radius_lg)
+        .border_1()
+        .border_color(cx.theme().primary)
+        .bg(cx.theme().secondary)
+        .child(
+            Label::new(SharedString::from(format!("Allow {peer_name} to connect?")))
+                .text_lg()
+                .font_weight(FontWeight::BOLD),
+        )
+        .children((queued_approvals > 0).then(|| {
+            Label::new(SharedString::from(format!(
+                "{queued_approvals} more waiting"
+            )))
+            .text_sm()
+            .text_color(cx.theme().muted_foreground)
+        }))
+        .child(
+            h_flex()
+                .gap_2()
+                .child(answer_button("approval-once", "Allow once", Decision::AllowOnce).primary())
+                .child(answer_button("approval-always", "Always", Decision::AllowAlways).outline())
+                .child(answer_button("approval-deny", "Deny", Decision::Deny).outline()),
+        )
+}
+
 fn render_service_card(
     view: Entity<Data>,
-    configured_transport: TransportKind,
-    running_transport_label: &'static str,
+    enabled_transports: Vec<TransportKind>,
+    running_transport_label: SharedString,
+    tls_fingerprint: Option<SharedString>,
+    pairing_pin: Option<SharedString>,
+    connection_qr: Option<AnyElement>,
     transport_button_label: &'static str,
     is_starting: bool,
     is_running: bool,
     cx: &mut Context<Data>,
 ) -> impl IntoElement {
     let transport_options = TransportKind::all();
+    let enabled_transports_label = if enabled_transports.is_empty() {
+        SharedString::from("None")
+    } else {
+        SharedString::from(
+            enabled_transports
+                .iter()
+                .map(|transport| transport.label())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    };
     let transport_menu_view = view.clone();
     let transport_toggle_view = view.clone();
 
@@ -130,8 +222,21 @@ fn render_service_card(
                             Label::new(running_transport_label)
                                 .text_xl()
                                 .font_weight(FontWeight::BOLD),
-                        ),
+                        )
+                        .children(tls_fingerprint.map(|fingerprint| {
+                            Label::new(SharedString::from(format!(
+                                "TLS fingerprint (SHA-256): {fingerprint}"
+                            )))
+                            .text_sm()
+                            .text_color(cx.theme().muted_foreground)
+                        }))
+                        .children(pairing_pin.map(|pin| {
+                            Label::new(SharedString::from(format!("Pairing PIN: {pin}")))
+                                .text_sm()
+                                .text_color(cx.theme().muted_foreground)
+                        })),
                 )
+                .children(connection_qr)
                 .child(
                     div()
                         .px_2()
@@ -165,32 +270,28 @@ fn render_service_card(
                         v_flex()
                             .gap_1()
                             .child(
-                                Label::new("Transport")
+                                Label::new("Transports")
                                     .text_sm()
                                     .text_color(cx.theme().muted_foreground),
                             )
                             .child(
                                 Button::new("transport-select")
-                                    .label(configured_transport.label())
+                                    .label(enabled_transports_label)
                                     .dropdown_caret(true)
                                     .outline()
                                     .dropdown_menu(move |menu, _, _| {
                                         transport_options.iter().fold(menu, |menu, transport| {
+                                            let enabled = enabled_transports.contains(transport);
                                             menu.item(
                                                 PopupMenuItem::new(transport.label())
-                                                    .checked(*transport == configured_transport)
+                                                    .checked(enabled)
                                                     .on_click({
                                                         let view = transport_menu_view.clone();
                                                         let transport = *transport;
                                                         move |_, _, cx| {
                                                             view.update(cx, |data, cx| {
-                                                                data.apply_settings_change(
-                                                                    cx,
-                                                                    true,
-                                                                    |settings| {
-                                                                        settings.transport =
-                                                                            transport;
-                                                                    },
+                                                                data.set_transport_enabled(
+                                                                    transport, !enabled, cx,
                                                                 );
                                                             });
                                                         }
@@ -207,13 +308,131 @@ fn render_service_card(
                         .primary()
                         .on_click(move |_, _, cx| {
                             transport_toggle_view.update(cx, |data, cx| {
-                                data.toggle_transport(cx);
+                                data.toggle_transports(cx);
                             });
                         }),
                 ),
         )
 }
 
+/// QR code with the URLs of the first live network transport, which phones
+/// scan to connect
+#[cfg(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp")))]
+fn render_connection_qr(data: &Data, cx: &mut Context<Data>) -> Option<AnyElement> {
+    let modules: &QrModules = data.connection_qr.as_ref()?;
+    let module_size = px(3.0);
+
+    Some(
+        v_flex()
+            .gap_1()
+            .items_center()
+            .child(
+                // White quiet zone around the code, so it scans in dark mode too
+                v_flex()
+                    .p(module_size * 4.0)
+                    .bg(gpui::white())
+                    .rounded(cx.theme().radius)
+                    .children(modules.dark.chunks(modules.width).map(|row| {
+                        h_flex().children(row.iter().map(|dark| {
+                            div().size(module_size).bg(if *dark {
+                                gpui::black()
+                            } else {
+                                gpui::white()
+                            })
+                        }))
+                    })),
+            )
+            .child(
+                Label::new("Scan to connect")
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground),
+            )
+            .into_any_element(),
+    )
+}
+
+#[cfg(not(all(feature = "qr", any(feature = "ws", feature = "tcp", feature = "udp"))))]
+fn render_connection_qr(_: &Data, _: &mut Context<Data>) -> Option<AnyElement> {
+    None
+}
+
+fn render_peers_card(
+    view: Entity<Data>,
+    connected: Vec<PeerInfo>,
+    max_connections: usize,
+    cx: &mut Context<Data>,
+) -> impl IntoElement {
+    let title = if max_connections > 0 {
+        format!("Connected Phones ({} / {max_connections})", connected.len())
+    } else {
+        format!("Connected Phones ({})", connected.len())
+    };
+
+    v_flex()
+        .w_full()
+        .p_5()
+        .gap_3()
+        .rounded(cx.theme().radius_lg)
+        .border_1()
+        .border_color(cx.theme().border)
+        .bg(cx.theme().secondary)
+        .child(
+            Label::new(SharedString::from(title))
+                .text_sm()
+                .text_color(cx.theme().muted_foreground),
+        )
+        .children(connected.into_iter().map(|peer| {
+            let label = match &peer.address {
+                Some(address) if !peer.name.contains(address.as_str()) => {
+                    format!("{} ({address})", peer.name)
+                }
+                _ => peer.name.clone(),
+            };
+            let mut stats = format!("{:?} · {} messages", peer.kind, peer.messages);
+            if peer.throttled > 0 {
+                stats += &format!(", {} throttled", peer.throttled);
+            }
+            if peer.invalid > 0 {
+                stats += &format!(", {} invalid", peer.invalid);
+            }
+            let disconnect_id = peer.id;
+
+            h_flex()
+                .justify_between()
+                .items_center()
+                .gap_4()
+                .child(
+                    v_flex()
+                        .gap_1()
+                        .child(Label::new(SharedString::from(label)))
+                        .child(
+                            Label::new(SharedString::from(stats))
+                                .text_xs()
+                                .text_color(cx.theme().muted_foreground),
+                        ),
+                )
+                .child(
+                    h_flex()
+                        .gap_2()
+                        .child(
+                            Button::new(SharedString::from(format!("disconnect-{}", peer.id)))
+                                .label("Disconnect")
+                                .outline()
+                                .on_click(move |_, _, _| disconnect_peer(disconnect_id)),
+                        )
+                        .children(peer.address.is_some().then(|| {
+                            let view = view.clone();
+                            Button::new(SharedString::from(format!("block-{}", peer.id)))
+                                .label("Block")
+                                .outline()
+                                .on_click(move |_, _, cx| {
+                                    view.update(cx, |data, cx| data.block_peer(&peer, cx));
+                                })
+                        })),
+                )
+        }))
+}
+
 fn render_settings_shell(
     view: Entity<Data>,
     settings: Args,
@@ -245,7 +464,6 @@ fn build_settings(view: Entity<Data>, settings: Args, is_dark_mode: bool) -> imp
         Some(relay_page(view.clone(), &settings)),
         Some(input_page(view.clone(), &settings)),
         Some(appearance_page(view.clone(), is_dark_mode)),
-        #[cfg(target_os = "windows")]
         controller_page(view.clone(), &settings),
     ]
     .into_iter()
@@ -280,6 +498,69 @@ fn relay_page(view: Entity<Data>, settings: &Args) -> SettingPage {
                 }
             },
         ),
+        string_input_item(
+            "relay-bind",
+            "Bind addresses",
+            "Comma separated addresses or interface names the network transports listen on.",
+            SharedString::from(settings.bind.join(", ")),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let bind = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|entry| !entry.is_empty())
+                        .map(String::from)
+                        .collect::<Vec<_>>();
+                    if bind.is_empty() {
+                        return;
+                    }
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.bind = bind;
+                        });
+                    });
+                }
+            },
+        ),
+        string_input_item(
+            "relay-tcp-port",
+            "TCP port",
+            "TCP port used by the raw TCP transport.",
+            SharedString::from(settings.tcp_port.to_string()),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let Ok(port) = value.parse::<u16>() else {
+                        return;
+                    };
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.tcp_port = port;
+                        });
+                    });
+                }
+            },
+        ),
+        string_input_item(
+            "relay-udp-port",
+            "UDP port",
+            "UDP port used by the UDP transport.",
+            SharedString::from(settings.udp_port.to_string()),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let Ok(port) = value.parse::<u16>() else {
+                        return;
+                    };
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.udp_port = port;
+                        });
+                    });
+                }
+            },
+        ),
         SettingItem::new(
             "Disable tray",
             SettingField::<bool>::switch(move |_| disable_tray, {
@@ -300,90 +581,15 @@ fn relay_page(view: Entity<Data>, settings: &Args) -> SettingPage {
     if let Some(item) = bluetooth_channel_item(view.clone(), settings) {
         connection_items.push(item);
     }
+    if let Some(item) = mdns_item(view.clone(), settings) {
+        connection_items.push(item);
+    }
 
-    SettingPage::new("Transport").default_open(true).group(
-        SettingGroup::new()
-            .title("Connection")
-            .items(connection_items),
-    )
-}
-
-fn input_page(view: Entity<Data>, settings: &Args) -> SettingPage {
-    SettingPage::new("Input")
-        .description("Controller input handling behavior.")
-        .group(SettingGroup::new().title("Double Tap").items([
-            stepped_number_item(
-                "double-tap-timing",
-                "Double tap timing",
-                "Milliseconds allowed between taps. Use -1 to disable the behavior.",
-                settings.double_tap_timing as f64,
-                NumberFieldOptions {
-                    min: -1.0,
-                    max: i64::MAX as f64,
-                    step: 25.0,
-                },
-                {
-                    let view = view.clone();
-                    move |value, cx| {
-                        let timing = value.round().max(-1.0) as i64;
-                        view.update(cx, |data, cx| {
-                            data.apply_settings_change(cx, true, |settings| {
-                                settings.double_tap_timing = timing;
-                            });
-                        });
-                    }
-                },
-            ),
-            string_input_item(
-                "double-tap-postfix",
-                "Double tap postfix",
-                "Only buttons with this suffix participate in double-tap-to-hold.",
-                SharedString::from(settings.double_tap_postfix.clone()),
-                {
-                    let view = view.clone();
-                    move |value, cx| {
-                        view.update(cx, |data, cx| {
-                            data.apply_settings_change(cx, true, |settings| {
-                                settings.double_tap_postfix = value;
-                            });
-                        });
-                    }
-                },
-            ),
-        ]))
-}
-
-fn appearance_page(view: Entity<Data>, is_dark_mode: bool) -> SettingPage {
-    SettingPage::new("Appearance").group(SettingGroup::new().title("Theme").items([
-        SettingItem::new(
-            "Dark mode",
-            SettingField::<bool>::switch(move |_| is_dark_mode, {
-                let view = view.clone();
-                move |enabled, cx| {
-                    let mode = if enabled {
-                        ThemeMode::Dark
-                    } else {
-                        ThemeMode::Light
-                    };
-                    Theme::change(mode, None, cx);
-                    cx.refresh_windows();
-                    view.update(cx, |data, cx| {
-                        data.set_theme_mode(mode);
-                        cx.notify();
-                    });
-                }
-            }),
-        ),
-    ]))
-}
-
-#[cfg(all(feature = "bluetooth", target_os = "linux"))]
-fn bluetooth_channel_item(view: Entity<Data>, settings: &Args) -> Option<SettingItem> {
-    Some(stepped_number_item(
-        "bluetooth-rfcomm-channel",
-        "Bluetooth RFCOMM channel",
-        "RFCOMM channel used by the Linux Bluetooth transport.",
-        settings.bt_channel as f64,
+    connection_items.push(stepped_number_item(
+        "max-players",
+        "Player slots",
+        "Number of player slots. Known phones always get the slot they had before.",
+        settings.max_players as f64,
         NumberFieldOptions {
             min: 1.0,
             max: u8::MAX as f64,
@@ -392,65 +598,1057 @@ fn bluetooth_channel_item(view: Entity<Data>, settings: &Args) -> Option<Setting
         {
             let view = view.clone();
             move |value, cx| {
-                let channel = value.round().clamp(1.0, u8::MAX as f64) as u8;
+                let max_players = value.round().clamp(1.0, u8::MAX as f64) as u8;
                 view.update(cx, |data, cx| {
                     data.apply_settings_change(cx, true, |settings| {
-                        settings.bt_channel = channel;
+                        settings.max_players = max_players;
                     });
                 });
             }
         },
-    ))
-}
-
-#[cfg(not(all(feature = "bluetooth", target_os = "linux")))]
-fn bluetooth_channel_item(_: Entity<Data>, _: &Args) -> Option<SettingItem> {
-    None
-}
-
-#[cfg(target_os = "windows")]
-fn controller_page(view: Entity<Data>, settings: &Args) -> Option<SettingPage> {
-    use crate::controller::Backend;
-
-    let backend_options = Backend::all()
-        .iter()
-        .map(|backend| {
-            (
-                SharedString::from(backend.id()),
-                SharedString::from(backend.label()),
-            )
-        })
-        .collect::<Vec<_>>();
-    let backend_id = SharedString::from(settings.controller.backend.id());
-
-    let mut items = vec![
-        SettingItem::new(
-            "Backend",
-            SettingField::<SharedString>::dropdown(backend_options, move |_| backend_id.clone(), {
-                let view = view.clone();
-                move |value, cx| {
-                    let Some(backend) = Backend::from_id(value.as_ref()) else {
-                        return;
-                    };
-                    let _ = view.update(cx, |data, cx| {
-                        data.apply_settings_change(cx, true, |settings| {
-                            settings.controller.backend = backend;
-                        });
+    ));
+    connection_items.push(stepped_number_item(
+        "udp-timeout",
+        "UDP timeout",
+        "Milliseconds without a datagram after which a UDP phone's controller is removed.",
+        settings.udp_timeout_ms as f64,
+        NumberFieldOptions {
+            min: 100.0,
+            max: u32::MAX as f64,
+            step: 500.0,
+        },
+        {
+            let view = view.clone();
+            move |value, cx| {
+                let timeout = value.round().max(100.0) as u64;
+                view.update(cx, |data, cx| {
+                    data.apply_settings_change(cx, true, |settings| {
+                        settings.udp_timeout_ms = timeout;
                     });
-                }
-            })
-            .default_value(SharedString::from(Backend::default().id())),
-        )
-        .description("Virtual controller backend used for outgoing desktop gamepad events."),
-    ];
-
-    if let Some(item) = vjoy_item(view.clone(), settings) {
+                });
+            }
+        },
+    ));
+    connection_items.push(stepped_number_item(
+        "resume-grace",
+        "Reconnect grace period",
+        "Milliseconds a disconnected phone's controller is kept for it to reconnect. Use 0 to disable.",
+        settings.resume_grace_ms as f64,
+        NumberFieldOptions {
+            min: 0.0,
+            max: u32::MAX as f64,
+            step: 500.0,
+        },
+        {
+            let view = view.clone();
+            move |value, cx| {
+                let grace = value.round().max(0.0) as u64;
+                view.update(cx, |data, cx| {
+                    data.apply_settings_change(cx, true, |settings| {
+                        settings.resume_grace_ms = grace;
+                    });
+                });
+            }
+        },
+    ));
+
+    connection_items.push(stepped_number_item(
+        "idle-timeout",
+        "Idle timeout",
+        "Milliseconds without a message after which a phone's inputs are released and its connection ends. Use 0 to disable.",
+        settings.idle_timeout_ms as f64,
+        NumberFieldOptions {
+            min: 0.0,
+            max: u32::MAX as f64,
+            step: 5000.0,
+        },
+        {
+            let view = view.clone();
+            move |value, cx| {
+                let timeout = value.round().max(0.0) as u64;
+                view.update(cx, |data, cx| {
+                    data.apply_settings_change(cx, true, |settings| {
+                        settings.idle_timeout_ms = timeout;
+                    });
+                });
+            }
+        },
+    ));
+    connection_items.push(stepped_number_item(
+        "heartbeat-interval",
+        "Heartbeat interval",
+        "Milliseconds between pings to WebSocket phones. Use 0 to disable.",
+        settings.heartbeat_interval_ms as f64,
+        NumberFieldOptions {
+            min: 0.0,
+            max: u32::MAX as f64,
+            step: 1000.0,
+        },
+        {
+            let view = view.clone();
+            move |value, cx| {
+                let interval = value.round().max(0.0) as u64;
+                view.update(cx, |data, cx| {
+                    data.apply_settings_change(cx, true, |settings| {
+                        settings.heartbeat_interval_ms = interval;
+                    });
+                });
+            }
+        },
+    ));
+    connection_items.push(stepped_number_item(
+        "heartbeat-timeout",
+        "Heartbeat timeout",
+        "Milliseconds a WebSocket phone may leave pings unanswered before its inputs are released and its connection ends.",
+        settings.heartbeat_timeout_ms as f64,
+        NumberFieldOptions {
+            min: 1000.0,
+            max: u32::MAX as f64,
+            step: 1000.0,
+        },
+        {
+            let view = view.clone();
+            move |value, cx| {
+                let timeout = value.round().max(1000.0) as u64;
+                view.update(cx, |data, cx| {
+                    data.apply_settings_change(cx, true, |settings| {
+                        settings.heartbeat_timeout_ms = timeout;
+                    });
+                });
+            }
+        },
+    ));
+
+    connection_items.push(stepped_number_item(
+        "handshake-timeout",
+        "Handshake timeout",
+        "Milliseconds a connection may take to finish its handshake before it's dropped.",
+        settings.handshake_timeout_ms as f64,
+        NumberFieldOptions {
+            min: 500.0,
+            max: u32::MAX as f64,
+            step: 1000.0,
+        },
+        {
+            let view = view.clone();
+            move |value, cx| {
+                let timeout = value.round().max(500.0) as u64;
+                view.update(cx, |data, cx| {
+                    data.apply_settings_change(cx, true, |settings| {
+                        settings.handshake_timeout_ms = timeout;
+                    });
+                });
+            }
+        },
+    ));
+    connection_items.push(stepped_number_item(
+        "max-pending-handshakes",
+        "Pending handshakes",
+        "Handshakes a transport runs at once before it stops accepting new connections.",
+        settings.max_pending_handshakes as f64,
+        NumberFieldOptions {
+            min: 1.0,
+            max: 1024.0,
+            step: 1.0,
+        },
+        {
+            let view = view.clone();
+            move |value, cx| {
+                let max_pending = value.round().clamp(1.0, 1024.0) as usize;
+                view.update(cx, |data, cx| {
+                    data.apply_settings_change(cx, true, |settings| {
+                        settings.max_pending_handshakes = max_pending;
+                    });
+                });
+            }
+        },
+    ));
+
+    let mut page = SettingPage::new("Transport").default_open(true).group(
+        SettingGroup::new()
+            .title("Connection")
+            .items(connection_items),
+    );
+    for group in [
+        Some(pairing_group(view.clone(), settings)),
+        Some(approval_group(view.clone(), settings)),
+        Some(access_group(view.clone(), settings)),
+        Some(limits_group(view.clone(), settings)),
+        tls_group(view.clone(), settings),
+        mqtt_group(view.clone(), settings),
+        unix_group(view.clone(), settings),
+        serial_group(view, settings),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        page = page.group(group);
+    }
+    page
+}
+
+fn pairing_group(view: Entity<Data>, settings: &Args) -> SettingGroup {
+    let require_pairing = settings.require_pairing;
+
+    SettingGroup::new().title("Pairing").items([
+        SettingItem::new(
+            "Require pairing",
+            SettingField::<bool>::switch(move |_| require_pairing, {
+                let view = view.clone();
+                move |value, cx| {
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.require_pairing = value;
+                        });
+                    });
+                }
+            })
+            .default_value(false),
+        )
+        .description("Phones have to present the pairing PIN before they get a controller. Paired phones are remembered."),
+        string_input_item(
+            "pairing-pin",
+            "Pairing PIN",
+            "Fixed PIN or token. Leave empty to use a random PIN generated on every start.",
+            SharedString::from(settings.pairing_pin.clone().unwrap_or_default()),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let pin = Some(value.trim().to_string()).filter(|pin| !pin.is_empty());
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.pairing_pin = pin;
+                        });
+                    });
+                }
+            },
+        ),
+        SettingItem::render(move |_, _, cx| {
+            render_setting_row(
+                "Paired phones",
+                "Forgets every paired phone, so each has to present the PIN again.",
+                Button::new("forget-paired")
+                    .label("Forget all")
+                    .outline()
+                    .on_click({
+                        let view = view.clone();
+                        move |_, _, cx| view.read(cx).forget_paired_peers()
+                    })
+                    .into_any_element(),
+                cx.theme().muted_foreground,
+            )
+        }),
+    ])
+}
+
+fn approval_group(view: Entity<Data>, settings: &Args) -> SettingGroup {
+    let require_approval = settings.require_approval;
+    let default_options = vec![
+        (SharedString::from("allow"), SharedString::from("Allow")),
+        (SharedString::from("deny"), SharedString::from("Deny")),
+    ];
+    let default_id = SharedString::from(match settings.approval_default {
+        PeerPolicy::Allow => "allow",
+        PeerPolicy::Deny => "deny",
+    });
+
+    SettingGroup::new().title("Approval").items([
+        SettingItem::new(
+            "Ask before connecting",
+            SettingField::<bool>::switch(move |_| require_approval, {
+                let view = view.clone();
+                move |value, cx| {
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.require_approval = value;
+                        });
+                    });
+                }
+            })
+            .default_value(false),
+        )
+        .description("Shows a prompt before a new phone gets a controller."),
+        SettingItem::new(
+            "When unanswered",
+            SettingField::<SharedString>::dropdown(default_options, move |_| default_id.clone(), {
+                let view = view.clone();
+                move |value, cx| {
+                    let policy = match value.as_ref() {
+                        "deny" => PeerPolicy::Deny,
+                        _ => PeerPolicy::Allow,
+                    };
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.approval_default = policy;
+                        });
+                    });
+                }
+            })
+            .default_value(SharedString::from("allow")),
+        )
+        .description("What happens to a phone when the prompt times out."),
+        stepped_number_item(
+            "approval-timeout",
+            "Prompt timeout",
+            "Milliseconds to wait for an answer before using the default.",
+            settings.approval_timeout_ms as f64,
+            NumberFieldOptions {
+                min: 1000.0,
+                max: u32::MAX as f64,
+                step: 5000.0,
+            },
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let timeout = value.round().max(1000.0) as u64;
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.approval_timeout_ms = timeout;
+                        });
+                    });
+                }
+            },
+        ),
+        SettingItem::render(move |_, _, cx| {
+            render_setting_row(
+                "Remembered answers",
+                "Forgets every phone allowed or denied for good, so each is asked again.",
+                Button::new("forget-approvals")
+                    .label("Forget all")
+                    .outline()
+                    .on_click({
+                        let view = view.clone();
+                        move |_, _, cx| view.read(cx).forget_peer_policies()
+                    })
+                    .into_any_element(),
+                cx.theme().muted_foreground,
+            )
+        }),
+    ])
+}
+
+fn access_group(view: Entity<Data>, settings: &Args) -> SettingGroup {
+    let list_item = |id, title, description, value: &[String], set: fn(&mut Args, Vec<String>)| {
+        string_input_item(
+            id,
+            title,
+            description,
+            SharedString::from(value.join(", ")),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let entries = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|entry| !entry.is_empty())
+                        .map(String::from)
+                        .collect::<Vec<_>>();
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| set(settings, entries));
+                    });
+                }
+            },
+        )
+    };
+
+    SettingGroup::new().title("Access").items([
+        list_item(
+            "allowlist",
+            "Allowlist",
+            "Comma separated IP addresses, CIDR ranges or Bluetooth addresses (* matches anything). When set, only these can connect.",
+            &settings.allowlist,
+            |settings, entries| settings.allowlist = entries,
+        ),
+        list_item(
+            "blocklist",
+            "Blocklist",
+            "Comma separated entries, in the same format, that are always rejected.",
+            &settings.blocklist,
+            |settings, entries| settings.blocklist = entries,
+        ),
+    ])
+}
+
+fn limits_group(view: Entity<Data>, settings: &Args) -> SettingGroup {
+    let action_options = vec![
+        (
+            SharedString::from("throttle"),
+            SharedString::from("Throttle"),
+        ),
+        (
+            SharedString::from("disconnect"),
+            SharedString::from("Disconnect"),
+        ),
+    ];
+    let action_id = SharedString::from(match settings.rate_limit_action {
+        RateLimitAction::Throttle => "throttle",
+        RateLimitAction::Disconnect => "disconnect",
+    });
+    let policy_options = vec![
+        (SharedString::from("skip"), SharedString::from("Skip")),
+        (
+            SharedString::from("skip-with-limit"),
+            SharedString::from("Skip up to the limit"),
+        ),
+        (
+            SharedString::from("disconnect"),
+            SharedString::from("Disconnect"),
+        ),
+    ];
+    let policy_id = SharedString::from(match settings.parse_error_policy {
+        ParseErrorPolicy::Skip => "skip",
+        ParseErrorPolicy::SkipWithLimit => "skip-with-limit",
+        ParseErrorPolicy::Disconnect => "disconnect",
+    });
+    let count_options = || NumberFieldOptions {
+        min: 0.0,
+        max: 1024.0,
+        step: 1.0,
+    };
+
+    SettingGroup::new().title("Limits").items([
+        stepped_number_item(
+            "max-connections",
+            "Connections",
+            "Phones connected at once across all transports. Use 0 for no limit.",
+            settings.max_connections as f64,
+            count_options(),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let max = value.round().clamp(0.0, 1024.0) as usize;
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.max_connections = max;
+                        });
+                    });
+                }
+            },
+        ),
+        stepped_number_item(
+            "max-connections-per-transport",
+            "Connections per transport",
+            "Phones connected at once over a single transport. Use 0 for no limit.",
+            settings.max_connections_per_transport as f64,
+            count_options(),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let max = value.round().clamp(0.0, 1024.0) as usize;
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.max_connections_per_transport = max;
+                        });
+                    });
+                }
+            },
+        ),
+        stepped_number_item(
+            "max-message-rate",
+            "Message rate",
+            "Messages per second a phone may send. Use 0 for no limit.",
+            settings.max_message_rate as f64,
+            NumberFieldOptions {
+                min: 0.0,
+                max: u32::MAX as f64,
+                step: 50.0,
+            },
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let rate = value.round().max(0.0) as u32;
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.max_message_rate = rate;
+                        });
+                    });
+                }
+            },
+        ),
+        SettingItem::new(
+            "Over the rate",
+            SettingField::<SharedString>::dropdown(action_options, move |_| action_id.clone(), {
+                let view = view.clone();
+                move |value, cx| {
+                    let action = match value.as_ref() {
+                        "disconnect" => RateLimitAction::Disconnect,
+                        _ => RateLimitAction::Throttle,
+                    };
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.rate_limit_action = action;
+                        });
+                    });
+                }
+            })
+            .default_value(SharedString::from("throttle")),
+        )
+        .description(
+            "Throttle holds messages back until they fit the rate, Disconnect drops the phone.",
+        ),
+        SettingItem::new(
+            "Invalid messages",
+            SettingField::<SharedString>::dropdown(policy_options, move |_| policy_id.clone(), {
+                let view = view.clone();
+                move |value, cx| {
+                    let policy = match value.as_ref() {
+                        "skip" => ParseErrorPolicy::Skip,
+                        "disconnect" => ParseErrorPolicy::Disconnect,
+                        _ => ParseErrorPolicy::SkipWithLimit,
+                    };
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.parse_error_policy = policy;
+                        });
+                    });
+                }
+            })
+            .default_value(SharedString::from("skip-with-limit")),
+        )
+        .description("Whether a phone sending messages that can't be understood keeps its connection."),
+        stepped_number_item(
+            "max-parse-errors",
+            "Invalid message limit",
+            "Invalid messages a phone may send before it's disconnected, when skipping up to the limit.",
+            settings.max_parse_errors as f64,
+            NumberFieldOptions {
+                min: 0.0,
+                max: u32::MAX as f64,
+                step: 10.0,
+            },
+            move |value, cx| {
+                let limit = value.round().max(0.0) as u64;
+                view.update(cx, |data, cx| {
+                    data.apply_settings_change(cx, true, |settings| {
+                        settings.max_parse_errors = limit;
+                    });
+                });
+            },
+        ),
+    ])
+}
+
+#[cfg(feature = "tls")]
+fn tls_group(view: Entity<Data>, settings: &Args) -> Option<SettingGroup> {
+    let tls = settings.tls;
+    let path_item =
+        |id, title, description, value: Option<&PathBuf>, set: fn(&mut Args, Option<PathBuf>)| {
+            string_input_item(
+                id,
+                title,
+                description,
+                SharedString::from(
+                    value
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_default(),
+                ),
+                {
+                    let view = view.clone();
+                    move |value, cx| {
+                        let path = Some(value.trim())
+                            .filter(|value| !value.is_empty())
+                            .map(PathBuf::from);
+                        view.update(cx, |data, cx| {
+                            data.apply_settings_change(cx, true, |settings| set(settings, path));
+                        });
+                    }
+                },
+            )
+        };
+
+    Some(SettingGroup::new().title("TLS").items([
+        SettingItem::new(
+            "Use TLS",
+            SettingField::<bool>::switch(move |_| tls, {
+                let view = view.clone();
+                move |value, cx| {
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.tls = value;
+                        });
+                    });
+                }
+            })
+            .default_value(false),
+        )
+        .description("Serves the WebSocket transport as wss://. The certificate fingerprint is shown while running."),
+        path_item(
+            "tls-cert",
+            "Certificate",
+            "PEM certificate chain. Leave both paths empty to use a generated self-signed certificate.",
+            settings.tls_cert.as_ref(),
+            |settings, path| settings.tls_cert = path,
+        ),
+        path_item(
+            "tls-key",
+            "Private key",
+            "PEM private key for the certificate.",
+            settings.tls_key.as_ref(),
+            |settings, path| settings.tls_key = path,
+        ),
+    ]))
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_group(_: Entity<Data>, _: &Args) -> Option<SettingGroup> {
+    None
+}
+
+#[cfg(feature = "mqtt")]
+fn mqtt_group(view: Entity<Data>, settings: &Args) -> Option<SettingGroup> {
+    Some(SettingGroup::new().title("MQTT").items([
+        string_input_item(
+            "mqtt-host",
+            "Broker host",
+            "Host of the MQTT broker the phones publish to.",
+            SharedString::from(settings.mqtt_host.clone()),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let host = value.trim().to_string();
+                    if host.is_empty() {
+                        return;
+                    }
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.mqtt_host = host;
+                        });
+                    });
+                }
+            },
+        ),
+        string_input_item(
+            "mqtt-port",
+            "Broker port",
+            "Port of the MQTT broker.",
+            SharedString::from(settings.mqtt_port.to_string()),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let Ok(port) = value.parse::<u16>() else {
+                        return;
+                    };
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.mqtt_port = port;
+                        });
+                    });
+                }
+            },
+        ),
+        string_input_item(
+            "mqtt-topic",
+            "Topic filter",
+            "Topic filter to subscribe to. The level matched by `+` tells phones apart.",
+            SharedString::from(settings.mqtt_topic.clone()),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let topic = value.trim().to_string();
+                    if topic.is_empty() {
+                        return;
+                    }
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.mqtt_topic = topic;
+                        });
+                    });
+                }
+            },
+        ),
+        stepped_number_item(
+            "mqtt-timeout",
+            "Device timeout",
+            "Milliseconds without a message after which a phone's controller is removed.",
+            settings.mqtt_timeout_ms as f64,
+            NumberFieldOptions {
+                min: 100.0,
+                max: u32::MAX as f64,
+                step: 1000.0,
+            },
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let timeout = value.round().max(100.0) as u64;
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.mqtt_timeout_ms = timeout;
+                        });
+                    });
+                }
+            },
+        ),
+    ]))
+}
+
+#[cfg(not(feature = "mqtt"))]
+fn mqtt_group(_: Entity<Data>, _: &Args) -> Option<SettingGroup> {
+    None
+}
+
+#[cfg(all(unix, feature = "unix"))]
+fn unix_group(view: Entity<Data>, settings: &Args) -> Option<SettingGroup> {
+    Some(
+        SettingGroup::new().title("Unix Socket").items([
+            string_input_item(
+                "unix-socket",
+                "Socket path",
+                "Where local tools connect. Empty for droidpad-gamepad.sock in $XDG_RUNTIME_DIR.",
+                SharedString::from(
+                    settings
+                        .unix_socket
+                        .as_ref()
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_default(),
+                ),
+                {
+                    let view = view.clone();
+                    move |value, cx| {
+                        let path = Some(value.trim())
+                            .filter(|value| !value.is_empty())
+                            .map(PathBuf::from);
+                        view.update(cx, |data, cx| {
+                            data.apply_settings_change(cx, true, |settings| {
+                                settings.unix_socket = path;
+                            });
+                        });
+                    }
+                },
+            ),
+            string_input_item(
+                "unix-socket-mode",
+                "File mode",
+                "Octal permissions of the socket, which decide who may connect. 600 is only you.",
+                SharedString::from(settings.unix_socket_mode.clone()),
+                {
+                    let view = view.clone();
+                    move |value, cx| {
+                        let mode = value.trim().to_string();
+                        if u32::from_str_radix(&mode, 8).is_err() {
+                            return;
+                        }
+                        view.update(cx, |data, cx| {
+                            data.apply_settings_change(cx, true, |settings| {
+                                settings.unix_socket_mode = mode;
+                            });
+                        });
+                    }
+                },
+            ),
+        ]),
+    )
+}
+
+#[cfg(not(all(unix, feature = "unix")))]
+fn unix_group(_: Entity<Data>, _: &Args) -> Option<SettingGroup> {
+    None
+}
+
+#[cfg(feature = "serial")]
+fn serial_group(view: Entity<Data>, settings: &Args) -> Option<SettingGroup> {
+    Some(SettingGroup::new().title("Serial").items([
+        string_input_item(
+            "serial-device",
+            "Device",
+            "Character device to read, such as /dev/rfcomm0 or a USB serial adapter.",
+            SharedString::from(settings.serial_device.clone()),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let device = value.trim().to_string();
+                    if device.is_empty() {
+                        return;
+                    }
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.serial_device = device;
+                        });
+                    });
+                }
+            },
+        ),
+        string_input_item(
+            "serial-baud-rate",
+            "Baud rate",
+            "Speed of the serial line. Bluetooth and pty devices ignore it.",
+            SharedString::from(settings.serial_baud_rate.to_string()),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let Ok(baud_rate) = value.trim().parse::<u32>() else {
+                        return;
+                    };
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.serial_baud_rate = baud_rate;
+                        });
+                    });
+                }
+            },
+        ),
+        stepped_number_item(
+            "serial-reopen",
+            "Reopen interval",
+            "Milliseconds between attempts to open the device while it's missing.",
+            settings.serial_reopen_ms as f64,
+            NumberFieldOptions {
+                min: 100.0,
+                max: 60000.0,
+                step: 500.0,
+            },
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let interval = value.round().max(100.0) as u64;
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.serial_reopen_ms = interval;
+                        });
+                    });
+                }
+            },
+        ),
+    ]))
+}
+
+#[cfg(not(feature = "serial"))]
+fn serial_group(_: Entity<Data>, _: &Args) -> Option<SettingGroup> {
+    None
+}
+
+fn input_page(view: Entity<Data>, settings: &Args) -> SettingPage {
+    SettingPage::new("Input")
+        .description("Controller input handling behavior.")
+        .group(SettingGroup::new().title("Double Tap").items([
+            stepped_number_item(
+                "double-tap-timing",
+                "Double tap timing",
+                "Milliseconds allowed between taps. Use -1 to disable the behavior.",
+                settings.double_tap_timing as f64,
+                NumberFieldOptions {
+                    min: -1.0,
+                    max: i64::MAX as f64,
+                    step: 25.0,
+                },
+                {
+                    let view = view.clone();
+                    move |value, cx| {
+                        let timing = value.round().max(-1.0) as i64;
+                        view.update(cx, |data, cx| {
+                            data.apply_settings_change(cx, true, |settings| {
+                                settings.double_tap_timing = timing;
+                            });
+                        });
+                    }
+                },
+            ),
+            string_input_item(
+                "double-tap-postfix",
+                "Double tap postfix",
+                "Only buttons with this suffix participate in double-tap-to-hold.",
+                SharedString::from(settings.double_tap_postfix.clone()),
+                {
+                    let view = view.clone();
+                    move |value, cx| {
+                        view.update(cx, |data, cx| {
+                            data.apply_settings_change(cx, true, |settings| {
+                                settings.double_tap_postfix = value;
+                            });
+                        });
+                    }
+                },
+            ),
+        ]))
+        .group(shared_device_group(view, settings))
+}
+
+fn shared_device_group(view: Entity<Data>, settings: &Args) -> SettingGroup {
+    let policy_options = vec![
+        (
+            SharedString::from("last-writer"),
+            SharedString::from("Last writer wins"),
+        ),
+        (
+            SharedString::from("largest-magnitude"),
+            SharedString::from("Largest magnitude wins"),
+        ),
+    ];
+    let policy_id = SharedString::from(match settings.shared_axis_policy {
+        AxisPolicy::LastWriter => "last-writer",
+        AxisPolicy::LargestMagnitude => "largest-magnitude",
+    });
+
+    SettingGroup::new().title("Shared Device").items([
+        string_input_item(
+            "shared-device",
+            "Shared device name",
+            "Merge every phone into one controller with this name. Leave empty for one controller per phone.",
+            SharedString::from(settings.shared_device.clone().unwrap_or_default()),
+            {
+                let view = view.clone();
+                move |value, cx| {
+                    let shared_device = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.shared_device = shared_device;
+                        });
+                    });
+                }
+            },
+        ),
+        SettingItem::new(
+            "Axis policy",
+            SettingField::<SharedString>::dropdown(policy_options, move |_| policy_id.clone(), {
+                let view = view.clone();
+                move |value, cx| {
+                    let policy = match value.as_ref() {
+                        "largest-magnitude" => AxisPolicy::LargestMagnitude,
+                        _ => AxisPolicy::LastWriter,
+                    };
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.shared_axis_policy = policy;
+                        });
+                    });
+                }
+            })
+            .default_value(SharedString::from("last-writer")),
+        )
+        .description("How stick values from several phones on the shared device are combined."),
+    ])
+}
+
+fn appearance_page(view: Entity<Data>, is_dark_mode: bool) -> SettingPage {
+    SettingPage::new("Appearance").group(SettingGroup::new().title("Theme").items([
+        SettingItem::new(
+            "Dark mode",
+            SettingField::<bool>::switch(move |_| is_dark_mode, {
+                let view = view.clone();
+                move |enabled, cx| {
+                    let mode = if enabled {
+                        ThemeMode::Dark
+                    } else {
+                        ThemeMode::Light
+                    };
+                    Theme::change(mode, None, cx);
+                    cx.refresh_windows();
+                    view.update(cx, |data, cx| {
+                        data.set_theme_mode(mode);
+                        cx.notify();
+                    });
+                }
+            }),
+        ),
+    ]))
+}
+
+#[cfg(all(feature = "bluetooth", target_os = "linux"))]
+fn bluetooth_channel_item(view: Entity<Data>, settings: &Args) -> Option<SettingItem> {
+    Some(stepped_number_item(
+        "bluetooth-rfcomm-channel",
+        "Bluetooth RFCOMM channel",
+        "RFCOMM channel used by the Linux Bluetooth transport.",
+        settings.bt_channel as f64,
+        NumberFieldOptions {
+            min: 1.0,
+            max: u8::MAX as f64,
+            step: 1.0,
+        },
+        {
+            let view = view.clone();
+            move |value, cx| {
+                let channel = value.round().clamp(1.0, u8::MAX as f64) as u8;
+                view.update(cx, |data, cx| {
+                    data.apply_settings_change(cx, true, |settings| {
+                        settings.bt_channel = channel;
+                    });
+                });
+            }
+        },
+    ))
+}
+
+#[cfg(not(all(feature = "bluetooth", target_os = "linux")))]
+fn bluetooth_channel_item(_: Entity<Data>, _: &Args) -> Option<SettingItem> {
+    None
+}
+
+#[cfg(feature = "mdns")]
+fn mdns_item(view: Entity<Data>, settings: &Args) -> Option<SettingItem> {
+    let advertise = !settings.disable_mdns;
+
+    Some(
+        SettingItem::new(
+            "Advertise on the network",
+            SettingField::<bool>::switch(
+                move |_| advertise,
+                move |value, cx| {
+                    view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.disable_mdns = !value;
+                        });
+                    });
+                },
+            )
+            .default_value(true),
+        )
+        .description(
+            "Announces the network transports over mDNS so phones can find this computer.",
+        ),
+    )
+}
+
+#[cfg(not(feature = "mdns"))]
+fn mdns_item(_: Entity<Data>, _: &Args) -> Option<SettingItem> {
+    None
+}
+
+fn controller_page(view: Entity<Data>, settings: &Args) -> Option<SettingPage> {
+    use crate::controller::Backend;
+
+    let backend_options = Backend::all()
+        .iter()
+        .map(|backend| {
+            (
+                SharedString::from(backend.id()),
+                SharedString::from(backend.label()),
+            )
+        })
+        .collect::<Vec<_>>();
+    let backend_id = SharedString::from(settings.controller.backend.id());
+
+    let mut items = vec![
+        SettingItem::new(
+            "Backend",
+            SettingField::<SharedString>::dropdown(backend_options, move |_| backend_id.clone(), {
+                let view = view.clone();
+                move |value, cx| {
+                    let Some(backend) = Backend::from_id(value.as_ref()) else {
+                        return;
+                    };
+                    let _ = view.update(cx, |data, cx| {
+                        data.apply_settings_change(cx, true, |settings| {
+                            settings.controller.backend = backend;
+                        });
+                    });
+                }
+            })
+            .default_value(SharedString::from(Backend::default().id())),
+        )
+        .description("Virtual controller backend used for outgoing desktop gamepad events."),
+    ];
+
+    if let Some(item) = vjoy_item(view.clone(), settings) {
+        items.push(item);
+    }
+
+    if let Some(item) = uhid_descriptor_item(view.clone(), settings) {
         items.push(item);
     }
 
     Some(
         SettingPage::new("Controller")
-            .description("Choose the virtual controller backend used on Windows.")
+            .description("Choose the virtual controller backend.")
             .group(SettingGroup::new().title("Output").items(items)),
     )
 }
@@ -481,7 +1679,6 @@ fn vjoy_item(view: Entity<Data>, settings: &Args) -> Option<SettingItem> {
     ))
 }
 
-#[cfg(target_os = "windows")]
 trait BackendUiExt {
     fn id(self) -> &'static str;
     fn label(self) -> &'static str;
@@ -493,6 +1690,56 @@ trait BackendUiExt {
         Self: Sized;
 }
 
+#[cfg(not(all(target_os = "windows", feature = "vjoy")))]
+fn vjoy_item(_: Entity<Data>, _: &Args) -> Option<SettingItem> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn uhid_descriptor_item(view: Entity<Data>, settings: &Args) -> Option<SettingItem> {
+    use crate::controller::UhidDescriptor;
+
+    let descriptor_options = vec![
+        (SharedString::from("generic"), SharedString::from("Generic")),
+        (SharedString::from("xbox"), SharedString::from("Xbox")),
+    ];
+    let descriptor_id = SharedString::from(match settings.controller.uhid_descriptor {
+        UhidDescriptor::Generic => "generic",
+        UhidDescriptor::Xbox => "xbox",
+    });
+
+    Some(
+        SettingItem::new(
+            "uhid descriptor",
+            SettingField::<SharedString>::dropdown(
+                descriptor_options,
+                move |_| descriptor_id.clone(),
+                {
+                    let view = view.clone();
+                    move |value, cx| {
+                        let descriptor = match value.as_ref() {
+                            "xbox" => UhidDescriptor::Xbox,
+                            _ => UhidDescriptor::Generic,
+                        };
+                        let _ = view.update(cx, |data, cx| {
+                            data.apply_settings_change(cx, true, |settings| {
+                                settings.controller.uhid_descriptor = descriptor;
+                            });
+                        });
+                    }
+                },
+            )
+            .default_value(SharedString::from("generic")),
+        )
+        .description("HID report descriptor used when the uhid backend is selected."),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn uhid_descriptor_item(_: Entity<Data>, _: &Args) -> Option<SettingItem> {
+    None
+}
+
 #[cfg(target_os = "windows")]
 impl BackendUiExt for crate::controller::Backend {
     fn id(self) -> &'static str {
@@ -501,6 +1748,8 @@ impl BackendUiExt for crate::controller::Backend {
             Self::Vigem => "vigem",
             #[cfg(feature = "vjoy")]
             Self::Vjoy => "vjoy",
+            Self::Null => "null",
+            Self::Log => "log",
         }
     }
 
@@ -510,6 +1759,8 @@ impl BackendUiExt for crate::controller::Backend {
             Self::Vigem => "ViGEm",
             #[cfg(feature = "vjoy")]
             Self::Vjoy => "vJoy",
+            Self::Null => "Null",
+            Self::Log => "Log",
         }
     }
 
@@ -519,6 +1770,8 @@ impl BackendUiExt for crate::controller::Backend {
             Self::Vigem,
             #[cfg(feature = "vjoy")]
             Self::Vjoy,
+            Self::Null,
+            Self::Log,
         ]
     }
 
@@ -530,6 +1783,38 @@ impl BackendUiExt for crate::controller::Backend {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl BackendUiExt for crate::controller::Backend {
+    fn id(self) -> &'static str {
+        match self {
+            Self::Uinput => "uinput",
+            Self::Uhid => "uhid",
+            Self::Null => "null",
+            Self::Log => "log",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Uinput => "uinput",
+            Self::Uhid => "uhid",
+            Self::Null => "Null",
+            Self::Log => "Log",
+        }
+    }
+
+    fn all() -> &'static [Self] {
+        &[Self::Uinput, Self::Uhid, Self::Null, Self::Log]
+    }
+
+    fn from_id(value: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|backend| backend.id() == value)
+    }
+}
+
 struct StringInputFieldState {
     input: Entity<InputState>,
     _subscription: Subscription,
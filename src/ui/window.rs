@@ -3,9 +3,12 @@ use gpui::{
 };
 use gpui_component::{ActiveTheme, Root, Theme, ThemeMode};
 use gpui_platform::application;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
 use crate::{
-    app::{SettingsManager, TransportKind, init_logging},
+    app::{SettingsManager, connected_peers, init_logging, set_prompt_handler, watch_peers},
+    controller::register_builtin_backends,
     ui::{
         state::Data,
         tray::{Tray, TrayEvent},
@@ -14,6 +17,10 @@ use crate::{
 
 pub fn run() {
     init_logging();
+    if let Err(err) = register_builtin_backends() {
+        log::error!("{err}");
+        return;
+    }
     application()
         .with_quit_mode(QuitMode::Explicit)
         .run(|cx: &mut App| {
@@ -40,15 +47,69 @@ pub fn run() {
                     move |_, event, app| match event {
                         TrayEvent::ToggleWindow => toggle_window(app, view.clone()),
                         TrayEvent::Exit => quit_app(app, true),
-                        TrayEvent::StartWS => start_transport(app, view.clone(), TransportKind::Ws),
-                        TrayEvent::StartBluetooth => {
-                            start_transport(app, view.clone(), TransportKind::Bluetooth)
+                        TrayEvent::ToggleTransport(kind) => {
+                            view.update(app, |data, cx| data.toggle_transport(*kind, cx))
                         }
                     }
                 })
                 .detach();
+
+                // Transports also start and stop from the window, and on
+                // their own when they fail
+                cx.observe(&view, {
+                    let tray = tray_emitter.clone();
+                    move |view, app| {
+                        let running = view.read(app).running_transports.keys().copied().collect();
+                        tray.update(app, |tray, _| tray.set_running(running));
+                    }
+                })
+                .detach();
             }
 
+            let (approvals, mut approval_requests) = mpsc::unbounded_channel();
+            set_prompt_handler(approvals);
+            cx.spawn({
+                let view = view.clone();
+                async move |cx| {
+                    while let Some(request) = approval_requests.recv().await {
+                        cx.update(|app| {
+                            view.update(app, |data, cx| data.push_approval(request, cx));
+                            // The prompt has to be seen, even while hidden to the tray
+                            if view.read(app).main_window.is_none() {
+                                toggle_window(app, view.clone());
+                            }
+                        });
+                    }
+                }
+            })
+            .detach();
+
+            cx.spawn({
+                let view = view.clone();
+                async move |cx| {
+                    let mut peers = watch_peers();
+                    while peers.changed().await.is_ok() {
+                        view.update(cx, |_, cx| cx.notify());
+                    }
+                }
+            })
+            .detach();
+
+            // Message counters change without any event, so connected peers
+            // get refreshed every second
+            cx.spawn({
+                let view = view.clone();
+                async move |cx| {
+                    loop {
+                        cx.background_executor().timer(Duration::from_secs(1)).await;
+                        if !connected_peers().is_empty() {
+                            view.update(cx, |_, cx| cx.notify());
+                        }
+                    }
+                }
+            })
+            .detach();
+
             cx.on_window_closed({
                 let view = view.clone();
                 let tray_active = tray.is_some();
@@ -86,16 +147,6 @@ fn quit_app(app: &mut App, _tray_active: bool) {
     app.quit();
 }
 
-fn start_transport(app: &mut App, view: Entity<Data>, kind: TransportKind) {
-    view.update(app, |data, cx| {
-        if data.running_transport.is_some() {
-            return;
-        };
-        data.apply_settings_change(cx, true, |settings| settings.transport = kind);
-        data.start_transport(cx);
-    });
-}
-
 fn toggle_window(app: &mut App, view: Entity<Data>) {
     let existing_window = view.read(app).main_window;
 